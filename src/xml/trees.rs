@@ -1,3 +1,7 @@
+pub mod arena;
+pub mod document;
 pub mod generators;
 pub mod nodes;
+pub mod prolog;
+pub mod span;
 pub mod tree;
@@ -0,0 +1,193 @@
+use std::marker::PhantomData;
+
+use crate::xml::trees::{nodes::node_interface::NodeInterface, tree::XmlTree};
+
+/// A node of an `ArenaTree`. Ids follow document order and are only
+/// meaningful for the tree they came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId(usize);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ArenaNode<T> {
+    node: T,
+    parent: Option<NodeId>,
+    /// The position among the children of `parent`.
+    index: usize,
+    children: Vec<NodeId>,
+}
+
+/// A tree kept as a flat list of nodes linked by `NodeId`, so a node can
+/// reach its parent and siblings. Convert from and to `XmlTree` with `From`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArenaTree<'a, T: NodeInterface<'a>> {
+    nodes: Vec<ArenaNode<T>>,
+    _marker: PhantomData<&'a ()>,
+}
+
+impl<'a, T: NodeInterface<'a>> ArenaTree<'a, T> {
+    pub fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+    /// Panics when `id` is from another tree.
+    pub fn node(&self, id: NodeId) -> &T {
+        &self.nodes[id.0].node
+    }
+    pub fn node_mut(&mut self, id: NodeId) -> &mut T {
+        &mut self.nodes[id.0].node
+    }
+    /// `None` for the root.
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].parent
+    }
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        &self.nodes[id.0].children
+    }
+    /// The parent, its parent and so on up to the root.
+    pub fn ancestors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        let nodes = &self.nodes;
+        std::iter::successors(self.parent(id), move |id| nodes[id.0].parent)
+    }
+    /// The position of the node among the children of its parent.
+    pub fn index_in_parent(&self, id: NodeId) -> Option<usize> {
+        self.parent(id).map(|_| self.nodes[id.0].index)
+    }
+    pub fn next_sibling(&self, id: NodeId) -> Option<NodeId> {
+        let index = self.index_in_parent(id)?;
+        self.children(self.parent(id)?).get(index + 1).copied()
+    }
+    pub fn prev_sibling(&self, id: NodeId) -> Option<NodeId> {
+        let index = self.index_in_parent(id)?.checked_sub(1)?;
+        self.children(self.parent(id)?).get(index).copied()
+    }
+    /// Every node in document order.
+    pub fn iter(&self) -> impl Iterator<Item = (NodeId, &T)> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (NodeId(i), &node.node))
+    }
+    pub fn get_elements_by_key_value(&self, key: &str, value: &str) -> Vec<NodeId> {
+        self.iter()
+            .filter(|(_, node)| node.contains_key_value(key, value))
+            .map(|(id, _)| id)
+            .collect()
+    }
+    pub fn get_elements_by_node_value(&self, value: &str) -> Vec<NodeId> {
+        self.iter()
+            .filter(|(_, node)| node.value() == value)
+            .map(|(id, _)| id)
+            .collect()
+    }
+    /// Add `tree` with its descendants in document order.
+    fn push(&mut self, tree: XmlTree<'a, T>, parent: Option<NodeId>) -> NodeId {
+        let id = NodeId(self.nodes.len());
+        let (node, children) = tree.into_parts();
+        self.nodes.push(ArenaNode {
+            node,
+            parent,
+            index: 0,
+            children: Vec::with_capacity(children.len()),
+        });
+        for (index, child) in children.into_iter().enumerate() {
+            let child = self.push(child, Some(id));
+            self.nodes[child.0].index = index;
+            self.nodes[id.0].children.push(child);
+        }
+        id
+    }
+}
+
+impl<'a, T: NodeInterface<'a>> From<XmlTree<'a, T>> for ArenaTree<'a, T> {
+    fn from(tree: XmlTree<'a, T>) -> Self {
+        let mut arena = ArenaTree {
+            nodes: Vec::new(),
+            _marker: PhantomData,
+        };
+        arena.push(tree, None);
+        arena
+    }
+}
+
+impl<'a, T: NodeInterface<'a>> From<ArenaTree<'a, T>> for XmlTree<'a, T> {
+    fn from(arena: ArenaTree<'a, T>) -> Self {
+        fn build<'a, T: NodeInterface<'a>>(
+            nodes: &mut [Option<ArenaNode<T>>],
+            id: NodeId,
+        ) -> XmlTree<'a, T> {
+            let ArenaNode { node, children, .. } = nodes[id.0].take().unwrap();
+            let mut tree = XmlTree::new(node, None);
+            for child in children {
+                tree.append_children(build(nodes, child));
+            }
+            tree
+        }
+        let mut nodes: Vec<_> = arena.nodes.into_iter().map(Some).collect();
+        build(&mut nodes, NodeId(0))
+    }
+}
+
+#[cfg(test)]
+mod arena_tests {
+    use crate::xml::trees::{
+        generators::xml_generator::XmlGenerator,
+        nodes::node_interface::{ElementInterface, PropertyInterface},
+    };
+
+    use super::*;
+    #[test]
+    fn navigation_test() {
+        let source = r#"<ul id="list"><li>one</li><li id="two">two</li><li>three</li></ul>"#;
+        let arena = ArenaTree::from(XmlGenerator::gen(source));
+        let root = arena.root();
+        assert_eq!(arena.node(root).value(), "ul");
+        assert_eq!(arena.parent(root), None);
+        assert_eq!(arena.children(root).len(), 3);
+
+        let two = arena.get_elements_by_key_value("id", "two")[0];
+        assert_eq!(arena.parent(two), Some(root));
+        assert_eq!(arena.index_in_parent(two), Some(1));
+        let one = arena.prev_sibling(two).unwrap();
+        let three = arena.next_sibling(two).unwrap();
+        assert_eq!(arena.prev_sibling(one), None);
+        assert_eq!(arena.next_sibling(three), None);
+        assert_eq!(arena.node(arena.children(three)[0]).value(), "three");
+
+        let text = arena.children(two)[0];
+        assert_eq!(arena.ancestors(text).collect::<Vec<_>>(), vec![two, root]);
+        assert_eq!(arena.index_in_parent(root), None);
+        assert_eq!(
+            arena.get_elements_by_node_value("li"),
+            vec![one, two, three]
+        );
+        assert_eq!(arena.iter().count(), 7);
+    }
+    #[test]
+    fn wide_sibling_test() {
+        let rows = "<row/>".repeat(50_000);
+        let source = format!("<sheetData>{}</sheetData>", rows);
+        let arena = ArenaTree::from(XmlGenerator::gen(&source));
+        let start = std::time::Instant::now();
+        let first = arena.children(arena.root())[0];
+        let walked = std::iter::successors(Some(first), |id| arena.next_sibling(*id)).count();
+        assert_eq!(walked, 50_000);
+        let last = *arena.children(arena.root()).last().unwrap();
+        assert_eq!(arena.index_in_parent(last), Some(49_999));
+        // a scan of the siblings per step takes minutes here
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+    }
+    #[test]
+    fn conversion_test() {
+        let source = "<a><b x='1'><c/>text</b><!-- note --><d/></a>";
+        let tree = XmlGenerator::gen(source);
+        let mut arena = ArenaTree::from(tree.clone());
+        assert_eq!(XmlTree::from(arena.clone()), tree);
+
+        let b = arena.get_elements_by_node_value("b")[0];
+        arena.node_mut(b).add_property("y", "2");
+        let changed = XmlTree::from(arena);
+        assert_eq!(
+            changed.to_xml_string(),
+            r#"<a><b x="1" y="2"><c/>text</b><!-- note --><d/></a>"#
+        );
+    }
+}
@@ -0,0 +1,41 @@
+use crate::xml::trees::{
+    nodes::concreate_nodes::quick_node::QuickNode, prolog::Prolog, tree::XmlTree,
+};
+
+/// A whole xml source: the prolog, the root element and what follows it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct XmlDocument<'a> {
+    pub prolog: Prolog<'a>,
+    pub root: XmlTree<'a, QuickNode<'a>>,
+    /// Comments and processing instructions after the root, in source order.
+    pub epilog: Vec<XmlTree<'a, QuickNode<'a>>>,
+}
+
+impl<'a> XmlDocument<'a> {
+    pub fn new(root: XmlTree<'a, QuickNode<'a>>) -> Self {
+        XmlDocument {
+            prolog: Prolog::default(),
+            root,
+            epilog: Vec::new(),
+        }
+    }
+    /// Write the document back as xml, one top level node per line.
+    /// Parsing the result gives an equal document.
+    pub fn to_xml_string(&self) -> String {
+        let prolog = &self.prolog;
+        let (before, after) = prolog
+            .nodes
+            .split_at(prolog.doctype_index.min(prolog.nodes.len()));
+        prolog
+            .declaration
+            .iter()
+            .map(|declaration| declaration.to_xml_string())
+            .chain(before.iter().map(|node| node.to_xml_string()))
+            .chain(prolog.doctype.iter().map(|doctype| doctype.to_xml_string()))
+            .chain(after.iter().map(|node| node.to_xml_string()))
+            .chain(std::iter::once(self.root.to_xml_string()))
+            .chain(self.epilog.iter().map(|node| node.to_xml_string()))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
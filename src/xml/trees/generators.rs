@@ -1,3 +1,10 @@
-pub(self) mod token;
-pub mod xml_generator;
+pub mod diagnostic;
+mod encoding;
+pub mod namespace;
+pub mod parse_error;
+pub mod parse_options;
+mod reference;
+mod token;
 pub(super) mod token_array;
+mod well_formed;
+pub mod xml_generator;
@@ -0,0 +1,72 @@
+use std::fmt::Display;
+
+use crate::xml::trees::span::Span;
+
+use super::parse_error::ParseError;
+
+/// A problem found by a recovering parse, with what was done about it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The error a failing parse would have returned.
+    pub error: ParseError,
+    pub recovery: Recovery,
+}
+
+/// How a recovering parse carried on after a problem.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Recovery {
+    /// Open elements were closed by the end tag of an ancestor or at the
+    /// end of the source.
+    AutoClosed,
+    /// The markup was left out of the tree.
+    Skipped,
+    /// The markup was kept as it was written, like a stray `&` in text.
+    KeptAsWritten,
+}
+
+impl Diagnostic {
+    pub fn new(error: ParseError, recovery: Recovery) -> Self {
+        Diagnostic { error, recovery }
+    }
+    pub fn span(&self) -> Span {
+        self.error.span()
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let recovery = match self.recovery {
+            Recovery::AutoClosed => "closed automatically",
+            Recovery::Skipped => "skipped",
+            Recovery::KeptAsWritten => "kept as written",
+        };
+        write!(f, "{} ({})", self.error, recovery)
+    }
+}
+
+/// Where recoverable errors go while parsing. `None` fails on the first
+/// error, otherwise errors are recorded and parsing carries on.
+pub(super) type Diagnostics<'d> = Option<&'d mut Vec<Diagnostic>>;
+
+/// Return `error` when failing fast, or record it as recovered by `recovery`.
+pub(super) fn recover(
+    diagnostics: &mut Diagnostics,
+    error: ParseError,
+    recovery: Recovery,
+) -> Result<(), ParseError> {
+    match diagnostics {
+        Some(diagnostics) => {
+            diagnostics.push(Diagnostic::new(error, recovery));
+            Ok(())
+        }
+        None => Err(error),
+    }
+}
+
+/// Record `error` as recovered by `recovery` when recovering, for problems
+/// that the options repair anyway.
+pub(super) fn note(diagnostics: &mut Diagnostics, error: ParseError, recovery: Recovery) {
+    if let Some(diagnostics) = diagnostics {
+        diagnostics.push(Diagnostic::new(error, recovery));
+    }
+}
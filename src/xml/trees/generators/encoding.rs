@@ -0,0 +1,209 @@
+use std::borrow::Cow;
+
+use crate::xml::trees::span::{Position, Span};
+
+use super::parse_error::ParseError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+    Windows1252,
+}
+
+impl Encoding {
+    fn name(&self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Latin1 => "ISO-8859-1",
+            Encoding::Windows1252 => "windows-1252",
+        }
+    }
+}
+
+/// Windows-1252 characters for 0x80 to 0x9F. The five unassigned bytes
+/// map to the C1 controls, like browsers do.
+const WINDOWS_1252: [char; 32] = [
+    '\u{20AC}', '\u{81}', '\u{201A}', '\u{192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2C6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8D}', '\u{17D}', '\u{8F}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2DC}', '\u{2122}', '\u{161}', '\u{203A}', '\u{153}', '\u{9D}', '\u{17E}', '\u{178}',
+];
+
+/// Decode `bytes` to a string. A byte order mark or the byte pattern of
+/// `<?` picks UTF-16, otherwise the `encoding` of the xml declaration is
+/// used, defaulting to UTF-8. The byte order mark is not kept and UTF-8
+/// is borrowed as is.
+pub(super) fn decode_bytes(bytes: &[u8]) -> Result<Cow<'_, str>, ParseError> {
+    let (encoding, body) = match bytes {
+        [0xEF, 0xBB, 0xBF, rest @ ..] => (Encoding::Utf8, rest),
+        [0xFF, 0xFE, rest @ ..] => (Encoding::Utf16Le, rest),
+        [0xFE, 0xFF, rest @ ..] => (Encoding::Utf16Be, rest),
+        [b'<', 0, b'?', 0, ..] => (Encoding::Utf16Le, bytes),
+        [0, b'<', 0, b'?', ..] => (Encoding::Utf16Be, bytes),
+        _ => (declared_encoding(bytes)?, bytes),
+    };
+    match encoding {
+        Encoding::Utf8 => match std::str::from_utf8(body) {
+            Ok(source) => Ok(Cow::Borrowed(source)),
+            Err(e) => {
+                let valid = std::str::from_utf8(&body[..e.valid_up_to()]).unwrap();
+                Err(invalid_bytes(encoding, valid))
+            }
+        },
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            let units = body.chunks(2).map(|pair| match (encoding, pair) {
+                (Encoding::Utf16Le, [low, high]) => u16::from_le_bytes([*low, *high]),
+                (_, [high, low]) => u16::from_be_bytes([*high, *low]),
+                // an odd trailing byte can not be a code unit
+                _ => 0xDC00,
+            });
+            let mut source = String::with_capacity(body.len() / 2);
+            for c in char::decode_utf16(units) {
+                match c {
+                    Ok(c) => source.push(c),
+                    Err(_) => return Err(invalid_bytes(encoding, &source)),
+                }
+            }
+            Ok(Cow::Owned(source))
+        }
+        Encoding::Latin1 => Ok(Cow::Owned(body.iter().map(|b| *b as char).collect())),
+        Encoding::Windows1252 => Ok(Cow::Owned(
+            body.iter()
+                .map(|b| match b {
+                    0x80..=0x9F => WINDOWS_1252[(b - 0x80) as usize],
+                    _ => *b as char,
+                })
+                .collect(),
+        )),
+    }
+}
+
+/// The encoding named in `<?xml ... encoding="name"?>` at the start of an
+/// ASCII compatible source.
+fn declared_encoding(bytes: &[u8]) -> Result<Encoding, ParseError> {
+    let Some(declaration) = bytes
+        .strip_prefix(b"<?xml")
+        .and_then(|rest| rest.split(|b| *b == b'>').next())
+    else {
+        return Ok(Encoding::Utf8);
+    };
+    let declaration = String::from_utf8_lossy(declaration);
+    let Some((_, after)) = declaration.split_once("encoding") else {
+        return Ok(Encoding::Utf8);
+    };
+    let name = after
+        .trim_start()
+        .strip_prefix('=')
+        .map(str::trim_start)
+        .and_then(|value| {
+            let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+            value[1..].split(quote).next()
+        })
+        .unwrap_or_default();
+    match name.to_ascii_lowercase().as_str() {
+        "utf-8" | "utf8" | "us-ascii" | "ascii" => Ok(Encoding::Utf8),
+        "iso-8859-1" | "iso_8859-1" | "latin1" | "l1" => Ok(Encoding::Latin1),
+        "windows-1252" | "cp1252" => Ok(Encoding::Windows1252),
+        _ => {
+            let start = Position::default().advanced("<?xml");
+            Err(ParseError::UnsupportedEncoding {
+                encoding: name.to_string(),
+                span: Span::new(start, start),
+            })
+        }
+    }
+}
+
+/// The error for bytes that are not valid in `encoding`, after `valid` was decoded.
+fn invalid_bytes(encoding: Encoding, valid: &str) -> ParseError {
+    let position = Position::default().advanced(valid);
+    ParseError::InvalidEncoding {
+        encoding: encoding.name().to_string(),
+        span: Span::new(position, position),
+    }
+}
+
+#[cfg(test)]
+mod encoding_tests {
+    use super::*;
+    fn utf16(source: &str, little_endian: bool) -> Vec<u8> {
+        source
+            .encode_utf16()
+            .flat_map(|unit| {
+                if little_endian {
+                    unit.to_le_bytes()
+                } else {
+                    unit.to_be_bytes()
+                }
+            })
+            .collect()
+    }
+    #[test]
+    fn utf8_test() {
+        assert_eq!(decode_bytes("<a>あ</a>".as_bytes()).unwrap(), "<a>あ</a>");
+        assert_eq!(decode_bytes(b"\xEF\xBB\xBF<a/>").unwrap(), "<a/>");
+        assert_eq!(
+            decode_bytes(b"<a>\n\xFF</a>"),
+            Err(ParseError::InvalidEncoding {
+                encoding: "UTF-8".to_string(),
+                span: Span::new(
+                    Position {
+                        offset: 4,
+                        line: 2,
+                        column: 1
+                    },
+                    Position {
+                        offset: 4,
+                        line: 2,
+                        column: 1
+                    }
+                ),
+            })
+        );
+    }
+    #[test]
+    fn utf16_test() {
+        let source = r#"<?xml version="1.0" encoding="UTF-16"?><a>é𝄞</a>"#;
+        for little_endian in [true, false] {
+            let bytes = utf16(source, little_endian);
+            assert_eq!(decode_bytes(&bytes).unwrap(), source);
+            let bom = utf16("\u{FEFF}", little_endian);
+            assert_eq!(decode_bytes(&[bom, bytes].concat()).unwrap(), source);
+        }
+        let mut bytes = utf16("<?xml version=\"1.0\"?><a>", true);
+        bytes.extend([0x00, 0xD8, b'<', 0]);
+        assert!(matches!(
+            decode_bytes(&bytes),
+            Err(ParseError::InvalidEncoding { .. })
+        ));
+    }
+    #[test]
+    fn single_byte_test() {
+        assert_eq!(
+            decode_bytes(b"<?xml version='1.0' encoding='ISO-8859-1'?><a>caf\xE9 \x80</a>")
+                .unwrap(),
+            "<?xml version='1.0' encoding='ISO-8859-1'?><a>café \u{80}</a>"
+        );
+        assert_eq!(
+            decode_bytes(
+                b"<?xml version=\"1.0\" encoding=\"Windows-1252\"?><a>\x80 \x93x\x94 \xE9</a>"
+            )
+            .unwrap(),
+            "<?xml version=\"1.0\" encoding=\"Windows-1252\"?><a>€ “x” é</a>"
+        );
+    }
+    #[test]
+    fn unsupported_test() {
+        let error =
+            decode_bytes(b"<?xml version=\"1.0\" encoding=\"Shift_JIS\"?><a/>").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "encoding Shift_JIS is not supported at line 1, column 6"
+        );
+    }
+}
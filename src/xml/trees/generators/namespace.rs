@@ -0,0 +1,223 @@
+use std::borrow::Cow;
+
+use crate::xml::trees::{
+    nodes::{concreate_nodes::quick_node::QuickNode, node_interface::NodeInterface},
+    span::Span,
+};
+
+use super::{
+    diagnostic::{recover, Diagnostics, Recovery},
+    parse_error::ParseError,
+    parse_options::ParseOptions,
+};
+
+pub const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+pub const XMLNS_NAMESPACE: &str = "http://www.w3.org/2000/xmlns/";
+
+/// The `xmlns` declarations in scope while the tree is built.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(super) struct Namespaces<'a> {
+    /// Prefix and uri, the default namespace has the empty prefix and
+    /// an empty uri undeclares it.
+    declared: Vec<(&'a str, Cow<'a, str>)>,
+    /// The length of `declared` before each open element.
+    frames: Vec<usize>,
+    /// Report prefixes without a declaration instead of leaving them
+    /// without a namespace.
+    check_prefixes: bool,
+}
+
+impl<'a> Namespaces<'a> {
+    pub fn new(options: &ParseOptions) -> Self {
+        Namespaces {
+            check_prefixes: options.check_well_formedness,
+            ..Default::default()
+        }
+    }
+    /// Declare the `xmlns` attributes of `node`, then resolve the prefixes
+    /// of its name and attributes. Call `leave` when the element closes.
+    /// Unbound prefixes are left without a namespace, and are only an
+    /// error when checking well-formedness and not recovering. An
+    /// `xmlns:` without a prefix is always an error, and declares nothing.
+    pub fn enter(
+        &mut self,
+        node: &mut QuickNode<'a>,
+        span: Span,
+        diagnostics: &mut Diagnostics,
+    ) -> Result<(), ParseError> {
+        self.frames.push(self.declared.len());
+        let Some(attributes) = node.attributes_mut() else {
+            return Ok(());
+        };
+        for attribute in attributes.iter() {
+            match attribute.key.strip_prefix("xmlns") {
+                Some("") => self.declared.push(("", attribute.value.clone())),
+                Some(":") => {
+                    let error = ParseError::EmptyPrefix { span };
+                    recover(diagnostics, error, Recovery::Skipped)?;
+                }
+                Some(prefix) if prefix.starts_with(':') => {
+                    self.declared.push((&prefix[1..], attribute.value.clone()))
+                }
+                _ => (),
+            }
+        }
+        for attribute in attributes.iter_mut() {
+            attribute.namespace_uri = match attribute.prefix() {
+                _ if attribute.key == "xmlns" => Some(Cow::Borrowed(XMLNS_NAMESPACE)),
+                Some(prefix) => self.resolve(prefix, span, diagnostics)?,
+                None => None,
+            };
+        }
+        let namespace_uri = match node.prefix() {
+            Some(prefix) => self.resolve(prefix, span, diagnostics)?,
+            None => self.default_namespace(),
+        };
+        node.set_namespace_uri(namespace_uri);
+        Ok(())
+    }
+    pub fn leave(&mut self) {
+        if let Some(len) = self.frames.pop() {
+            self.declared.truncate(len);
+        }
+    }
+    fn default_namespace(&self) -> Option<Cow<'a, str>> {
+        self.declared
+            .iter()
+            .rev()
+            .find(|(prefix, _)| prefix.is_empty())
+            .map(|(_, uri)| uri.clone())
+            .filter(|uri| !uri.is_empty())
+    }
+    fn resolve(
+        &self,
+        prefix: &str,
+        span: Span,
+        diagnostics: &mut Diagnostics,
+    ) -> Result<Option<Cow<'a, str>>, ParseError> {
+        let uri = match prefix {
+            "xml" => Some(Cow::Borrowed(XML_NAMESPACE)),
+            "xmlns" => Some(Cow::Borrowed(XMLNS_NAMESPACE)),
+            _ => self
+                .declared
+                .iter()
+                .rev()
+                .find(|(declared, _)| *declared == prefix)
+                .map(|(_, uri)| uri.clone())
+                .filter(|uri| !uri.is_empty()),
+        };
+        if uri.is_none() && self.check_prefixes {
+            let error = ParseError::UnboundPrefix {
+                prefix: prefix.to_string(),
+                span,
+            };
+            recover(diagnostics, error, Recovery::KeptAsWritten)?;
+        }
+        Ok(uri)
+    }
+}
+
+#[cfg(test)]
+mod namespace_tests {
+    use crate::xml::trees::{
+        generators::{
+            parse_options::ParseOptions,
+            reference::Entities,
+            token::{Token, TokenType},
+        },
+        nodes::node_interface::PropertyInterface,
+    };
+
+    use super::*;
+    fn element(value: &str) -> QuickNode<'_> {
+        Token::with_type(value, TokenType::Element)
+            .to_node(&ParseOptions::default(), &Entities::default())
+            .unwrap()
+    }
+    #[test]
+    fn enter_test() {
+        let mut namespaces = Namespaces::default();
+        let mut root = element(r#"root xmlns="urn:d" xmlns:r="urn:r" r:id="1" id="2""#);
+        namespaces
+            .enter(&mut root, Span::default(), &mut None)
+            .unwrap();
+        assert_eq!(root.prefix(), None);
+        assert_eq!(root.local_name(), "root");
+        assert_eq!(root.namespace_uri(), Some("urn:d"));
+        assert_eq!(root.get_ns("urn:r", "id"), Some("1"));
+        assert_eq!(root.get_ns("urn:d", "id"), None);
+        let attributes = root.attributes().unwrap();
+        assert_eq!(
+            attributes.get("xmlns:r").unwrap().namespace_uri.as_deref(),
+            Some(XMLNS_NAMESPACE)
+        );
+        assert_eq!(attributes.get("id").unwrap().namespace_uri, None);
+
+        let mut child = element(r#"r:child xmlns:r="urn:inner" xml:lang="en""#);
+        namespaces
+            .enter(&mut child, Span::default(), &mut None)
+            .unwrap();
+        assert_eq!(child.prefix(), Some("r"));
+        assert_eq!(child.local_name(), "child");
+        assert_eq!(child.namespace_uri(), Some("urn:inner"));
+        assert_eq!(child.get_ns(XML_NAMESPACE, "lang"), Some("en"));
+        namespaces.leave();
+
+        let mut child = element("r:child");
+        namespaces
+            .enter(&mut child, Span::default(), &mut None)
+            .unwrap();
+        assert_eq!(child.namespace_uri(), Some("urn:r"));
+        namespaces.leave();
+        namespaces.leave();
+
+        let mut child = element("r:child");
+        namespaces
+            .enter(&mut child, Span::default(), &mut None)
+            .unwrap();
+        assert_eq!(child.namespace_uri(), None);
+        assert_eq!(child.local_name(), "child");
+
+        let mut namespaces = Namespaces::new(&ParseOptions::strict());
+        let mut child = element("r:child");
+        assert_eq!(
+            namespaces.enter(&mut child, Span::default(), &mut None),
+            Err(ParseError::UnboundPrefix {
+                prefix: "r".to_string(),
+                span: Span::default()
+            })
+        );
+    }
+    #[test]
+    fn undeclare_default_test() {
+        let mut namespaces = Namespaces::default();
+        let mut root = element(r#"root xmlns="urn:d""#);
+        namespaces
+            .enter(&mut root, Span::default(), &mut None)
+            .unwrap();
+        let mut child = element(r#"child xmlns="""#);
+        namespaces
+            .enter(&mut child, Span::default(), &mut None)
+            .unwrap();
+        assert_eq!(child.namespace_uri(), None);
+    }
+    #[test]
+    fn empty_prefix_test() {
+        let mut namespaces = Namespaces::default();
+        let mut root = element(r#"a xmlns:="urn:x""#);
+        assert_eq!(
+            namespaces.enter(&mut root, Span::default(), &mut None),
+            Err(ParseError::EmptyPrefix {
+                span: Span::default()
+            })
+        );
+        let mut namespaces = Namespaces::default();
+        let mut root = element(r#"a xmlns:="urn:x""#);
+        let mut diagnostics = vec![];
+        namespaces
+            .enter(&mut root, Span::default(), &mut Some(&mut diagnostics))
+            .unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(root.namespace_uri(), None);
+    }
+}
@@ -0,0 +1,211 @@
+use std::fmt::Display;
+
+use crate::xml::trees::span::{Position, Span};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// The bytes name an encoding that can not be decoded.
+    UnsupportedEncoding { encoding: String, span: Span },
+    /// The bytes are not valid in their encoding, the span is where decoding stopped.
+    InvalidEncoding { encoding: String, span: Span },
+    /// An element was opened but the source ended before its end tag.
+    UnclosedTag { name: String, span: Span },
+    /// An end tag does not close the element that is currently open.
+    MismatchedEndTag {
+        expected: String,
+        expected_position: Position,
+        found: String,
+        span: Span,
+    },
+    /// An end tag appeared while no element was open.
+    UnexpectedEndTag { name: String, span: Span },
+    /// Character data appeared outside of the root element.
+    TextOutsideRoot { text: String, span: Span },
+    /// The source ended in the middle of a tag.
+    UnexpectedEof { span: Span },
+    /// The `<?xml ...?>` declaration has missing or unknown pseudo attributes.
+    InvalidDeclaration { span: Span },
+    /// The `<?xml ...?>` declaration is not at the start of the source.
+    MisplacedDeclaration { span: Span },
+    /// The `<!DOCTYPE ...>` declaration is malformed.
+    InvalidDoctype { span: Span },
+    /// The `<!DOCTYPE ...>` declaration is repeated or after the root element.
+    MisplacedDoctype { span: Span },
+    /// An `&` that does not start a known entity or character reference.
+    InvalidReference { reference: String, span: Span },
+    /// An entity reference that expands deeper or larger than the options allow.
+    EntityLimitExceeded { reference: String, span: Span },
+    /// An entity reference whose replacement text refers back to itself.
+    RecursiveEntity { reference: String, span: Span },
+    /// An attribute value that is not enclosed in quotes.
+    UnquotedAttributeValue { name: String, span: Span },
+    /// An attribute with `=` but no value after it, the span is its name.
+    MissingAttributeValue { name: String, span: Span },
+    /// An element has the same attribute more than once, the span is the later one.
+    DuplicateAttribute { name: String, span: Span },
+    /// A prefix of an element or attribute name without an `xmlns` declaration.
+    UnboundPrefix { prefix: String, span: Span },
+    /// An `xmlns:` declaration with nothing after the colon.
+    EmptyPrefix { span: Span },
+    /// A second element at the top level, the span is its start tag.
+    MultipleRoots { name: String, span: Span },
+    /// A well-formedness constraint broken, only checked in strict mode.
+    NotWellFormed { violation: Violation, span: Span },
+    /// Every error found in strict mode, in source order.
+    Multiple(Vec<ParseError>),
+}
+
+/// The XML 1.0 well-formedness constraints that are checked in strict mode.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Violation {
+    /// An element, attribute, target or doctype name that does not match
+    /// the `Name` production.
+    InvalidName(String),
+    /// A character outside of the `Char` production, written or referenced.
+    IllegalCharacter(char),
+    /// A `<` in the value of the named attribute.
+    LessThanInAttributeValue(String),
+    /// An attribute written without `=` and a value.
+    MissingAttributeValue(String),
+    /// Two attributes with no whitespace between them.
+    MissingWhitespace,
+    /// A `--` inside a comment, or a comment ending with `-`.
+    DoubleHyphenInComment,
+    /// A `]]>` in character data.
+    CDataEndInText,
+    /// A processing instruction target that is `xml` in any case.
+    ReservedTarget(String),
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::InvalidName(name) => write!(f, "{:?} is not a valid name", name),
+            Violation::IllegalCharacter(c) => {
+                write!(f, "character U+{:04X} is not allowed", *c as u32)
+            }
+            Violation::LessThanInAttributeValue(name) => {
+                write!(f, "value of attribute {} contains '<'", name)
+            }
+            Violation::MissingAttributeValue(name) => {
+                write!(f, "attribute {} has no value", name)
+            }
+            Violation::MissingWhitespace => write!(f, "attributes are not separated by whitespace"),
+            Violation::DoubleHyphenInComment => write!(f, "comment contains \"--\""),
+            Violation::CDataEndInText => write!(f, "text contains \"]]>\""),
+            Violation::ReservedTarget(target) => {
+                write!(f, "processing instruction target {} is reserved", target)
+            }
+        }
+    }
+}
+
+impl ParseError {
+    /// Where the error was found. For unclosed tags this is the start tag.
+    pub fn span(&self) -> Span {
+        match self {
+            ParseError::UnsupportedEncoding { span, .. }
+            | ParseError::InvalidEncoding { span, .. }
+            | ParseError::UnclosedTag { span, .. }
+            | ParseError::MismatchedEndTag { span, .. }
+            | ParseError::UnexpectedEndTag { span, .. }
+            | ParseError::TextOutsideRoot { span, .. }
+            | ParseError::UnexpectedEof { span }
+            | ParseError::InvalidDeclaration { span }
+            | ParseError::MisplacedDeclaration { span }
+            | ParseError::InvalidDoctype { span }
+            | ParseError::MisplacedDoctype { span }
+            | ParseError::InvalidReference { span, .. }
+            | ParseError::EntityLimitExceeded { span, .. }
+            | ParseError::RecursiveEntity { span, .. }
+            | ParseError::UnquotedAttributeValue { span, .. }
+            | ParseError::MissingAttributeValue { span, .. }
+            | ParseError::DuplicateAttribute { span, .. }
+            | ParseError::UnboundPrefix { span, .. }
+            | ParseError::EmptyPrefix { span }
+            | ParseError::MultipleRoots { span, .. }
+            | ParseError::NotWellFormed { span, .. } => *span,
+            ParseError::Multiple(errors) => {
+                errors.first().map(ParseError::span).unwrap_or_default()
+            }
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::UnsupportedEncoding { encoding, .. } => {
+                write!(f, "encoding {} is not supported", encoding)?
+            }
+            ParseError::InvalidEncoding { encoding, .. } => {
+                write!(f, "source is not valid {}", encoding)?
+            }
+            ParseError::UnclosedTag { name, .. } => write!(f, "tag <{}> is not closed", name)?,
+            ParseError::MismatchedEndTag {
+                expected,
+                expected_position,
+                found,
+                ..
+            } => write!(
+                f,
+                "end tag </{}> does not match open tag <{}> from line {}, column {}",
+                found, expected, expected_position.line, expected_position.column
+            )?,
+            ParseError::UnexpectedEndTag { name, .. } => {
+                write!(f, "end tag </{}> has no open tag", name)?
+            }
+            ParseError::TextOutsideRoot { text, .. } => {
+                write!(f, "text {:?} is outside of the root element", text)?
+            }
+            ParseError::UnexpectedEof { .. } => write!(f, "unexpected end of source inside a tag")?,
+            ParseError::InvalidDeclaration { .. } => write!(f, "xml declaration is invalid")?,
+            ParseError::MisplacedDeclaration { .. } => {
+                write!(f, "xml declaration must be at the start of the source")?
+            }
+            ParseError::InvalidDoctype { .. } => write!(f, "doctype declaration is invalid")?,
+            ParseError::MisplacedDoctype { .. } => {
+                write!(f, "doctype declaration must be before the root element")?
+            }
+            ParseError::InvalidReference { reference, .. } => {
+                write!(f, "reference {:?} is invalid", reference)?
+            }
+            ParseError::EntityLimitExceeded { reference, .. } => write!(
+                f,
+                "reference {:?} expands beyond the entity limits",
+                reference
+            )?,
+            ParseError::RecursiveEntity { reference, .. } => {
+                write!(f, "reference {:?} refers to itself", reference)?
+            }
+            ParseError::UnquotedAttributeValue { name, .. } => {
+                write!(f, "value of attribute {} is not quoted", name)?
+            }
+            ParseError::MissingAttributeValue { name, .. } => {
+                write!(f, "attribute {} has no value", name)?
+            }
+            ParseError::DuplicateAttribute { name, .. } => {
+                write!(f, "attribute {} is already defined", name)?
+            }
+            ParseError::UnboundPrefix { prefix, .. } => {
+                write!(f, "namespace prefix {} is not declared", prefix)?
+            }
+            ParseError::EmptyPrefix { .. } => {
+                write!(f, "namespace declaration xmlns: has no prefix")?
+            }
+            ParseError::MultipleRoots { name, .. } => {
+                write!(f, "element <{}> is after the root element", name)?
+            }
+            ParseError::NotWellFormed { violation, .. } => write!(f, "{}", violation)?,
+            // each error has its own location
+            ParseError::Multiple(errors) => {
+                let lines: Vec<_> = errors.iter().map(ParseError::to_string).collect();
+                return write!(f, "{}", lines.join("\n"));
+            }
+        };
+        let start = self.span().start;
+        write!(f, " at line {}, column {}", start.line, start.column)
+    }
+}
+
+impl std::error::Error for ParseError {}
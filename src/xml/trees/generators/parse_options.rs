@@ -0,0 +1,99 @@
+/// How end tags that do not close the open element are handled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EndTagMode {
+    /// Reject the source with `ParseError::MismatchedEndTag`.
+    #[default]
+    Strict,
+    /// Close every element opened after the matching one, like browsers do.
+    /// End tags without any matching open element are skipped.
+    AutoClose,
+}
+
+/// What happens to whitespace in text nodes. Elements with
+/// `xml:space="preserve"` always keep their whitespace.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WhitespacePolicy {
+    /// Keep every text node as written, including whitespace only ones.
+    Preserve,
+    /// Strip leading and trailing whitespace and drop text nodes that become empty.
+    #[default]
+    Trim,
+    /// Like `Trim`, and also replace each inner whitespace run with a single space.
+    Collapse,
+    /// Keep text as written but drop text nodes made only of whitespace.
+    DropWhitespaceOnly,
+}
+
+/// What happens when an element has the same attribute more than once.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DuplicateAttributePolicy {
+    /// Reject the source with `ParseError::DuplicateAttribute`.
+    #[default]
+    Error,
+    /// Keep the first value and skip the later ones, like browsers do.
+    FirstWins,
+    /// Keep the last value, at the position of the first one.
+    LastWins,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseOptions {
+    pub end_tag_mode: EndTagMode,
+    /// Leave `<!-- -->` comments out of the tree.
+    pub ignore_comments: bool,
+    /// Accept HTML style attribute values without quotes, like `width=100`.
+    pub unquoted_attribute_values: bool,
+    pub whitespace: WhitespacePolicy,
+    /// Attributes whose values are lists of tokens, split on whitespace
+    /// into the property values. Other values are kept whole.
+    pub tokenized_attributes: Vec<String>,
+    pub duplicate_attributes: DuplicateAttributePolicy,
+    /// How deep entities declared in the doctype may refer to other entities.
+    pub entity_depth_limit: usize,
+    /// How many bytes of entity replacement text a document may expand to.
+    pub entity_expansion_limit: usize,
+    /// Check the XML 1.0 well-formedness constraints the parser otherwise
+    /// lets through, like names, `--` in comments and namespace prefixes
+    /// without an `xmlns` declaration. Errors are then
+    /// reported together as `ParseError::Multiple`.
+    pub check_well_formedness: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            end_tag_mode: EndTagMode::default(),
+            ignore_comments: false,
+            unquoted_attribute_values: false,
+            whitespace: WhitespacePolicy::default(),
+            tokenized_attributes: vec!["class".to_string(), "mc:Ignorable".to_string()],
+            duplicate_attributes: DuplicateAttributePolicy::default(),
+            entity_depth_limit: 16,
+            entity_expansion_limit: 1 << 20,
+            check_well_formedness: false,
+        }
+    }
+}
+
+impl ParseOptions {
+    pub fn is_tokenized(&self, key: &str) -> bool {
+        self.tokenized_attributes.iter().any(|k| k == key)
+    }
+    /// Options that repair malformed markup instead of rejecting it.
+    pub fn lenient() -> Self {
+        ParseOptions {
+            end_tag_mode: EndTagMode::AutoClose,
+            unquoted_attribute_values: true,
+            duplicate_attributes: DuplicateAttributePolicy::FirstWins,
+            ..Default::default()
+        }
+    }
+    /// Options that reject every well-formedness violation, to validate
+    /// hand written documents.
+    pub fn strict() -> Self {
+        ParseOptions {
+            check_well_formedness: true,
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,344 @@
+use std::{borrow::Cow, cell::Cell, collections::HashMap, ops::Range};
+
+use super::{parse_options::ParseOptions, well_formed::is_char};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum ReferenceError {
+    /// Malformed, or not a predefined or declared entity.
+    Invalid,
+    /// The expansion went deeper or grew larger than the options allow.
+    LimitExceeded,
+    /// An entity that refers to itself.
+    Recursive,
+}
+
+/// The general entities declared in the internal subset of the doctype,
+/// with the limits that guard their expansion.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) struct Entities<'a> {
+    declared: HashMap<&'a str, &'a str>,
+    depth_limit: usize,
+    size_limit: usize,
+    /// Bytes of replacement text expanded so far in the document.
+    expanded: Cell<usize>,
+}
+
+impl Default for Entities<'_> {
+    fn default() -> Self {
+        Entities::new(&ParseOptions::default())
+    }
+}
+
+impl<'a> Entities<'a> {
+    pub fn new(options: &ParseOptions) -> Self {
+        Entities {
+            declared: HashMap::new(),
+            depth_limit: options.entity_depth_limit,
+            size_limit: options.entity_expansion_limit,
+            expanded: Cell::new(0),
+        }
+    }
+    /// Read the `<!ENTITY name "value">` declarations of an internal subset.
+    /// Parameter and external entities are skipped, so references to them
+    /// stay invalid. `None` when the subset is malformed.
+    pub fn declare(&mut self, subset: &'a str) -> Option<()> {
+        let mut rest = subset.trim_start();
+        while !rest.is_empty() {
+            rest = if let Some(comment) = rest.strip_prefix("<!--") {
+                comment.split_once("-->")?.1
+            } else if let Some(pi) = rest.strip_prefix("<?") {
+                pi.split_once("?>")?.1
+            } else if let Some(entity) = rest.strip_prefix("<!ENTITY") {
+                let (declaration, after) = split_declaration(entity)?;
+                if let Some((name, value)) = internal_entity(declaration) {
+                    self.declared.entry(name).or_insert(value);
+                }
+                after
+            } else if let Some(markup) = rest.strip_prefix("<!") {
+                split_declaration(markup)?.1
+            } else if let Some(reference) = rest.strip_prefix('%') {
+                reference.split_once(';')?.1
+            } else {
+                return None;
+            }
+            .trim_start();
+        }
+        Some(())
+    }
+    /// Decode the entity and character references in `raw`.
+    /// The source is borrowed as is when it has no `&` in it.
+    /// On failure the byte range of the reference in `raw` is returned,
+    /// which is only the `&` for a stray one.
+    pub fn decode<'s>(&self, raw: &'s str) -> Result<Cow<'s, str>, (Range<usize>, ReferenceError)> {
+        self.decode_with(raw, |_, _| false)
+    }
+    /// Like `decode`, but references that fail are kept as written.
+    /// Their byte ranges are returned with the decoded text.
+    pub fn decode_lossy<'s>(
+        &self,
+        raw: &'s str,
+    ) -> (Cow<'s, str>, Vec<(Range<usize>, ReferenceError)>) {
+        let mut failed = vec![];
+        let decoded = self.decode_with(raw, |range, error| {
+            failed.push((range, error));
+            true
+        });
+        (decoded.unwrap_or(Cow::Borrowed(raw)), failed)
+    }
+    /// Run `f` without counting its expansions against the size limit,
+    /// for text that was decoded once already.
+    pub fn uncounted<T>(&self, f: impl FnOnce() -> T) -> T {
+        let expanded = self.expanded.replace(0);
+        let result = f();
+        self.expanded.set(expanded);
+        result
+    }
+    /// Decode `raw`, asking `keep` what to do with each reference that
+    /// fails: keep it as written and carry on, or stop with it. A stray `&`
+    /// is passed to `keep` without the text after it.
+    fn decode_with<'s>(
+        &self,
+        raw: &'s str,
+        mut keep: impl FnMut(Range<usize>, ReferenceError) -> bool,
+    ) -> Result<Cow<'s, str>, (Range<usize>, ReferenceError)> {
+        let Some(first) = raw.find('&') else {
+            return Ok(Cow::Borrowed(raw));
+        };
+        let mut decoded = String::with_capacity(raw.len());
+        decoded.push_str(&raw[..first]);
+        let mut rest_start = first;
+        while let Some(amp) = raw[rest_start..].find('&').map(|i| i + rest_start) {
+            decoded.push_str(&raw[rest_start..amp]);
+            let (range, result) = match raw[amp..].find(';') {
+                Some(semi) => {
+                    let end = amp + semi;
+                    let len = decoded.len();
+                    let result = self.expand(&raw[amp + 1..end], &mut vec![], &mut decoded);
+                    if result.is_err() {
+                        decoded.truncate(len);
+                    }
+                    (amp..end + 1, result)
+                }
+                None => (amp..raw.len(), Err(ReferenceError::Invalid)),
+            };
+            if let Err(error) = result {
+                // a stray `&` is kept alone, so references after it still decode
+                let stray =
+                    raw[amp + 1..range.end].contains(|c: char| c.is_ascii_whitespace() || c == '&');
+                let kept = if stray { amp..amp + 1 } else { range.clone() };
+                if !keep(kept.clone(), error) {
+                    return Err((kept, error));
+                }
+                decoded.push('&');
+                rest_start = amp + 1;
+                continue;
+            }
+            rest_start = range.end;
+        }
+        decoded.push_str(&raw[rest_start..]);
+        Ok(Cow::Owned(decoded))
+    }
+    /// Push the replacement of `&name;` to `decoded`. `open` are the
+    /// entities whose replacement text is being expanded.
+    fn expand(
+        &self,
+        name: &str,
+        open: &mut Vec<&'a str>,
+        decoded: &mut String,
+    ) -> Result<(), ReferenceError> {
+        if let Some(c) = resolve(name) {
+            decoded.push(c);
+            return Ok(());
+        }
+        let Some((&name, &value)) = self.declared.get_key_value(name) else {
+            return Err(ReferenceError::Invalid);
+        };
+        if open.contains(&name) {
+            return Err(ReferenceError::Recursive);
+        }
+        let expanded = self.expanded.get() + value.len();
+        if open.len() >= self.depth_limit || expanded > self.size_limit {
+            return Err(ReferenceError::LimitExceeded);
+        }
+        self.expanded.set(expanded);
+        open.push(name);
+        let mut rest = value;
+        while let Some(amp) = rest.find('&') {
+            decoded.push_str(&rest[..amp]);
+            let (reference, after) = rest[amp + 1..]
+                .split_once(';')
+                .ok_or(ReferenceError::Invalid)?;
+            self.expand(reference, open, decoded)?;
+            rest = after;
+        }
+        decoded.push_str(rest);
+        open.pop();
+        Ok(())
+    }
+}
+
+/// Decode the predefined entities and numeric character references in `raw`.
+#[cfg(test)]
+pub(super) fn decode_references(raw: &str) -> Result<Cow<'_, str>, Range<usize>> {
+    Entities::default().decode(raw).map_err(|(range, _)| range)
+}
+
+fn resolve(name: &str) -> Option<char> {
+    match name {
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "amp" => Some('&'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        _ => {
+            let code = if let Some(hex) = name.strip_prefix("#x") {
+                u32::from_str_radix(hex, 16).ok()?
+            } else if let Some(dec) = name.strip_prefix('#') {
+                dec.parse().ok()?
+            } else {
+                return None;
+            };
+            char::from_u32(code).filter(|c| is_char(*c))
+        }
+    }
+}
+
+/// Split a markup declaration at its closing `>`, skipping quoted `>`.
+fn split_declaration(source: &str) -> Option<(&str, &str)> {
+    let mut quote = None;
+    for (i, c) in source.char_indices() {
+        match (quote, c) {
+            (Some(q), _) if c == q => quote = None,
+            (Some(_), _) => (),
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return Some((&source[..i], &source[i + 1..])),
+            _ => (),
+        }
+    }
+    None
+}
+
+/// The name and the value of `name "value"`, `None` for parameter and
+/// external entities.
+fn internal_entity(declaration: &str) -> Option<(&str, &str)> {
+    let declaration = declaration.trim();
+    let end = declaration.find(|c: char| c.is_ascii_whitespace())?;
+    let (name, rest) = declaration.split_at(end);
+    if name == "%" {
+        return None;
+    }
+    let rest = rest.trim_start();
+    let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let (value, _) = rest[1..].split_once(quote)?;
+    Some((name, value))
+}
+
+#[cfg(test)]
+mod reference_tests {
+    use super::*;
+    #[test]
+    fn decode_borrowed_test() {
+        let decoded = decode_references("plain text").unwrap();
+        assert!(matches!(decoded, Cow::Borrowed("plain text")));
+    }
+    #[test]
+    fn decode_entity_test() {
+        assert_eq!(
+            decode_references("&lt;a href=&quot;x&quot;&gt; &amp; &apos;").unwrap(),
+            r#"<a href="x"> & '"#
+        );
+        assert_eq!(decode_references("&#x41;&#65;&#X41;").unwrap_err(), 11..17);
+        assert_eq!(decode_references("&#x41;&#65;&#x3042;").unwrap(), "AAあ");
+    }
+    #[test]
+    fn decode_error_test() {
+        assert_eq!(decode_references("a & b").unwrap_err(), 2..3);
+        assert_eq!(decode_references("a & b; c").unwrap_err(), 2..3);
+        assert_eq!(decode_references("a &amp").unwrap_err(), 2..6);
+        assert_eq!(decode_references("a &unknown; b").unwrap_err(), 2..11);
+        assert_eq!(decode_references("&#0;").unwrap_err(), 0..4);
+        assert_eq!(decode_references("a&#1;").unwrap_err(), 1..5);
+        assert_eq!(decode_references("&#xFFFF;&#xD800;").unwrap_err(), 0..8);
+        assert_eq!(decode_references("&#9;&#x10FFFF;").unwrap(), "\t\u{10FFFF}");
+        assert_eq!(decode_references("&#xZZ;").unwrap_err(), 0..6);
+    }
+    #[test]
+    fn decode_lossy_test() {
+        let entities = Entities::default();
+        assert_eq!(
+            entities.decode_lossy("a & b &lt; &bad; &amp"),
+            (
+                Cow::Owned("a & b < &bad; &amp".to_string()),
+                vec![
+                    (2..3, ReferenceError::Invalid),
+                    (11..16, ReferenceError::Invalid),
+                    (17..21, ReferenceError::Invalid)
+                ]
+            )
+        );
+        assert_eq!(
+            entities.decode_lossy("plain"),
+            (Cow::Borrowed("plain"), vec![])
+        );
+    }
+    #[test]
+    fn declare_test() {
+        let mut entities = Entities::default();
+        let subset = r#"
+            <!-- a 'comment' -->
+            <!ELEMENT note (#PCDATA)>
+            <!ATTLIST note lang CDATA "a>b">
+            <!ENTITY company "Acme &amp; Co">
+            <!ENTITY company "ignored">
+            <!ENTITY full '&company; &#x41;'>
+            <!ENTITY % param "p">
+            %param;
+            <!ENTITY logo SYSTEM "logo.png">
+            <?pi data?>
+        "#;
+        assert_eq!(entities.declare(subset), Some(()));
+        assert_eq!(entities.decode("&full;!").unwrap(), "Acme & Co A!");
+        assert_eq!(
+            entities.decode("x &logo;"),
+            Err((2..8, ReferenceError::Invalid))
+        );
+        assert_eq!(entities.declare("<!ENTITY broken 'x'"), None);
+        assert_eq!(entities.declare("text"), None);
+    }
+    #[test]
+    fn expansion_limit_test() {
+        let mut entities = Entities::default();
+        entities.declare(
+            r#"<!ENTITY a "&b;"><!ENTITY b "&a;">
+               <!ENTITY lol "lol"><!ENTITY lol1 "&lol;&lol;&lol;&lol;&lol;&lol;&lol;&lol;">
+               <!ENTITY lol2 "&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;">
+               <!ENTITY lol3 "&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;">"#,
+        );
+        assert_eq!(
+            entities.decode("x &a;"),
+            Err((2..5, ReferenceError::Recursive))
+        );
+        assert_eq!(entities.decode("&lol2;").unwrap().len(), 3 * 64);
+        let options = ParseOptions {
+            entity_depth_limit: 2,
+            ..Default::default()
+        };
+        let mut shallow = Entities::new(&options);
+        shallow.declared = entities.declared.clone();
+        assert_eq!(shallow.decode("&lol1;").unwrap().len(), 24);
+        assert_eq!(
+            shallow.decode("&lol2;"),
+            Err((0..6, ReferenceError::LimitExceeded))
+        );
+        let options = ParseOptions {
+            entity_expansion_limit: 1000,
+            ..Default::default()
+        };
+        let mut small = Entities::new(&options);
+        small.declared = entities.declared.clone();
+        assert_eq!(
+            small.decode("&lol3;"),
+            Err((0..6, ReferenceError::LimitExceeded))
+        );
+    }
+}
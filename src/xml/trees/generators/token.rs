@@ -1,30 +1,202 @@
-use std::collections::HashMap;
+use std::{borrow::Cow, ops::Range};
 
-use crate::xml::trees::nodes::{concreate_nodes::quick_node::QuickNode, node_type::NodeType};
+use crate::xml::trees::{
+    nodes::{
+        attributes::{Attribute, Attributes},
+        concreate_nodes::quick_node::QuickNode,
+        node_type::NodeType,
+    },
+    prolog::{Doctype, XmlDeclaration},
+    span::{Position, Span},
+};
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+use super::{
+    diagnostic::{recover, Diagnostic, Diagnostics, Recovery},
+    parse_error::ParseError,
+    parse_options::{DuplicateAttributePolicy, ParseOptions, WhitespacePolicy},
+    reference::{Entities, ReferenceError},
+};
+
+#[derive(Clone, Debug, Eq)]
 pub(super) struct Token<'a> {
     pub(super) value: &'a str,
     pub(super) token_type: TokenType,
+    pub(super) span: Span,
+}
+
+// span is where the token was read from, so it does not take part in equality
+impl PartialEq for Token<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value && self.token_type == other.token_type
+    }
 }
 
 impl<'a> Token<'a> {
+    #[cfg(test)]
     pub fn with_type(value: &'a str, token_type: TokenType) -> Self {
-        Token { value, token_type }
+        Self::with_span(value, token_type, Span::default())
+    }
+    pub fn with_span(value: &'a str, token_type: TokenType, span: Span) -> Self {
+        Token {
+            value,
+            token_type,
+            span,
+        }
     }
-    pub fn to_node(self) -> QuickNode<'a> {
-        match &self.token_type {
-            TokenType::Element => self.element_token_to_node(),
-            TokenType::SingleElement => self.single_element_token_to_node(),
-            TokenType::Text => QuickNode::new(self.value, NodeType::Text),
+    #[cfg(test)]
+    pub fn to_node(
+        &self,
+        options: &ParseOptions,
+        entities: &Entities,
+    ) -> Result<QuickNode<'a>, ParseError> {
+        self.to_node_with(options, entities, &mut None)
+    }
+    /// Convert the token, recording the errors it can recover from in `diagnostics`.
+    pub fn to_node_with(
+        &self,
+        options: &ParseOptions,
+        entities: &Entities,
+        diagnostics: &mut Diagnostics,
+    ) -> Result<QuickNode<'a>, ParseError> {
+        let node = match &self.token_type {
+            TokenType::Element => self.element_token_to_node(options, entities, diagnostics)?,
+            TokenType::SingleElement => {
+                self.single_element_token_to_node(options, entities, diagnostics)?
+            }
+            TokenType::Text => {
+                QuickNode::text(self.value, self.decoded_value(entities, diagnostics)?)
+            }
+            TokenType::Comment => QuickNode::new(self.value, NodeType::Comment),
+            TokenType::CData => QuickNode::new(self.value, NodeType::CData),
+            TokenType::ProcessingInstruction => {
+                let (target, data) = split_name(self.value);
+                QuickNode::processing_instruction(target, data)
+            }
             _ => panic!("not consider end type"),
+        };
+        Ok(node.with_span(self.span))
+    }
+    /// Convert a text token, `None` when the whitespace policy drops it.
+    pub fn to_text_node(
+        &self,
+        whitespace: WhitespacePolicy,
+        entities: &Entities,
+        diagnostics: &mut Diagnostics,
+    ) -> Result<Option<QuickNode<'a>>, ParseError> {
+        let token = match whitespace {
+            WhitespacePolicy::Preserve => self.clone(),
+            _ if self.is_blank() => return Ok(None),
+            WhitespacePolicy::DropWhitespaceOnly => self.clone(),
+            WhitespacePolicy::Trim | WhitespacePolicy::Collapse => self.trimmed(),
+        };
+        let mut decoded = token.decoded_value(entities, diagnostics)?;
+        if whitespace == WhitespacePolicy::Collapse {
+            decoded = collapse_whitespace(decoded);
         }
+        Ok(Some(
+            QuickNode::text(token.value, decoded).with_span(token.span),
+        ))
+    }
+    pub fn is_blank(&self) -> bool {
+        self.token_type == TokenType::Text && self.value.bytes().all(|c| c.is_ascii_whitespace())
     }
-    fn element_token_to_node(self) -> QuickNode<'a> {
-        start_or_single_token_to_node(self)
+    /// The token without its leading and trailing whitespace.
+    pub fn trimmed(&self) -> Token<'a> {
+        let value = self
+            .value
+            .trim_start_matches(|c: char| c.is_ascii_whitespace());
+        let start = self
+            .span
+            .start
+            .advanced(&self.value[..self.value.len() - value.len()]);
+        let value = value.trim_end_matches(|c: char| c.is_ascii_whitespace());
+        Token::with_span(
+            value,
+            self.token_type.clone(),
+            Span::new(start, start.advanced(value)),
+        )
     }
-    fn single_element_token_to_node(self) -> QuickNode<'a> {
-        start_or_single_token_to_node(self)
+    fn decoded_value(
+        &self,
+        entities: &Entities,
+        diagnostics: &mut Diagnostics,
+    ) -> Result<Cow<'a, str>, ParseError> {
+        decode(entities, self.value, self.span.start, diagnostics)
+    }
+    pub fn to_declaration(&self) -> Result<XmlDeclaration<'a>, ParseError> {
+        let invalid = || ParseError::InvalidDeclaration { span: self.span };
+        let (_, data) = split_name(self.value);
+        let mut version = None;
+        let mut encoding = None;
+        let mut standalone = None;
+        for (key, value) in pseudo_attributes(data).ok_or_else(invalid)? {
+            match key {
+                "version" if version.is_none() => version = Some(value),
+                "encoding" if encoding.is_none() => encoding = Some(value),
+                "standalone" if standalone.is_none() => {
+                    standalone = match value {
+                        "yes" => Some(true),
+                        "no" => Some(false),
+                        _ => return Err(invalid()),
+                    }
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(XmlDeclaration {
+            version: version.ok_or_else(invalid)?,
+            encoding,
+            standalone,
+        })
+    }
+    pub fn to_doctype(&self) -> Result<Doctype<'a>, ParseError> {
+        let invalid = || ParseError::InvalidDoctype { span: self.span };
+        if !self.value.starts_with(|c: char| c.is_ascii_whitespace()) {
+            return Err(invalid());
+        }
+        let (name, rest) = split_name(self.value.trim_start());
+        if name.is_empty() {
+            return Err(invalid());
+        }
+        let mut doctype = Doctype::new(name);
+        let rest = if let Some(rest) = rest.strip_prefix("PUBLIC") {
+            let (public_id, rest) = quoted_literal(rest).ok_or_else(invalid)?;
+            let (system_id, rest) = quoted_literal(rest).ok_or_else(invalid)?;
+            doctype.public_id = Some(public_id);
+            doctype.system_id = Some(system_id);
+            rest
+        } else if let Some(rest) = rest.strip_prefix("SYSTEM") {
+            let (system_id, rest) = quoted_literal(rest).ok_or_else(invalid)?;
+            doctype.system_id = Some(system_id);
+            rest
+        } else {
+            rest
+        };
+        let rest = rest.trim();
+        if !rest.is_empty() {
+            let subset = rest
+                .strip_prefix('[')
+                .and_then(|rest| rest.strip_suffix(']'))
+                .ok_or_else(invalid)?;
+            doctype.internal_subset = Some(subset);
+        }
+        Ok(doctype)
+    }
+    fn element_token_to_node(
+        &self,
+        options: &ParseOptions,
+        entities: &Entities,
+        diagnostics: &mut Diagnostics,
+    ) -> Result<QuickNode<'a>, ParseError> {
+        start_or_single_token_to_node(self, options, entities, diagnostics)
+    }
+    fn single_element_token_to_node(
+        &self,
+        options: &ParseOptions,
+        entities: &Entities,
+        diagnostics: &mut Diagnostics,
+    ) -> Result<QuickNode<'a>, ParseError> {
+        start_or_single_token_to_node(self, options, entities, diagnostics)
     }
 }
 
@@ -34,6 +206,104 @@ pub(super) enum TokenType {
     EndElement,
     SingleElement,
     Text,
+    Comment,
+    CData,
+    Declaration,
+    ProcessingInstruction,
+    Doctype,
+}
+
+/// The error for the reference at `range` of `source`, which starts at `start`.
+fn invalid_reference(
+    start: Position,
+    source: &str,
+    range: Range<usize>,
+    error: ReferenceError,
+) -> ParseError {
+    let reference = &source[range.clone()];
+    let start = start.advanced(&source[..range.start]);
+    let span = Span::new(start, start.advanced(reference));
+    let reference = reference.to_string();
+    match error {
+        ReferenceError::Invalid => ParseError::InvalidReference { reference, span },
+        ReferenceError::LimitExceeded => ParseError::EntityLimitExceeded { reference, span },
+        ReferenceError::Recursive => ParseError::RecursiveEntity { reference, span },
+    }
+}
+
+/// Decode the references in `raw`, which starts at `start`. When
+/// recovering, the references that fail are kept as written.
+fn decode<'a>(
+    entities: &Entities,
+    raw: &'a str,
+    start: Position,
+    diagnostics: &mut Diagnostics,
+) -> Result<Cow<'a, str>, ParseError> {
+    let error = |(range, e)| invalid_reference(start, raw, range, e);
+    match diagnostics {
+        None => entities.decode(raw).map_err(error),
+        Some(diagnostics) => {
+            let (decoded, failed) = entities.decode_lossy(raw);
+            diagnostics.extend(
+                failed
+                    .into_iter()
+                    .map(|failed| Diagnostic::new(error(failed), Recovery::KeptAsWritten)),
+            );
+            Ok(decoded)
+        }
+    }
+}
+
+/// Replace every whitespace run with a single space, borrowing when nothing changes.
+fn collapse_whitespace(text: Cow<'_, str>) -> Cow<'_, str> {
+    let bytes = text.as_bytes();
+    let collapsed = !(bytes.first().is_some_and(u8::is_ascii_whitespace)
+        || bytes.last().is_some_and(u8::is_ascii_whitespace)
+        || bytes.iter().any(|c| c.is_ascii_whitespace() && *c != b' ')
+        || bytes.windows(2).any(|w| w[0] == b' ' && w[1] == b' '));
+    if collapsed {
+        return text;
+    }
+    Cow::Owned(text.split_ascii_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// Split `target data` of a processing instruction at the first whitespace.
+fn split_name(source: &str) -> (&str, &str) {
+    match source.find(|c: char| c.is_ascii_whitespace()) {
+        Some(i) => (&source[..i], source[i..].trim_start()),
+        None => (source, ""),
+    }
+}
+
+/// Read a `"literal"` after whitespace, returning it with the rest of `source`.
+fn quoted_literal(source: &str) -> Option<(&str, &str)> {
+    let rest = source.trim_start();
+    if rest.len() == source.len() {
+        return None;
+    }
+    let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    rest[1..].split_once(quote)
+}
+
+/// Read `key="value"` pairs of a declaration, `None` when they are malformed.
+fn pseudo_attributes(source: &str) -> Option<Vec<(&str, &str)>> {
+    let mut pairs = vec![];
+    let mut rest = source.trim_start();
+    while !rest.is_empty() {
+        let (key, after_key) = rest.split_once('=')?;
+        let after_key = after_key.trim_start();
+        let quote = after_key
+            .chars()
+            .next()
+            .filter(|c| *c == '"' || *c == '\'')?;
+        let (value, after_value) = after_key[1..].split_once(quote)?;
+        pairs.push((key.trim(), value));
+        if !(after_value.is_empty() || after_value.starts_with(|c: char| c.is_ascii_whitespace())) {
+            return None;
+        }
+        rest = after_value.trim_start();
+    }
+    Some(pairs)
 }
 #[derive(Debug, PartialEq, Eq, Clone)]
 enum StateMachine {
@@ -41,118 +311,248 @@ enum StateMachine {
     ValueChar,
     EleKeyBlank,
     EleKeyChar,
+    EleKeyEnd,
     EleValBlank,
     EleValStart,
     EleValChar,
     EleValSplit,
+    EleValUnquoted,
 }
 
-fn start_or_single_token_to_node<'a>(token: Token<'a>) -> QuickNode<'a> {
+#[allow(clippy::single_range_in_vec_init)]
+fn start_or_single_token_to_node<'a>(
+    token: &Token<'a>,
+    options: &ParseOptions,
+    entities: &Entities,
+    diagnostics: &mut Diagnostics,
+) -> Result<QuickNode<'a>, ParseError> {
     let mut key_range = 0..0;
+    let mut raw_start = 0;
+    let mut quote = b'"';
     let mut value_range_list = vec![];
-    let mut property = HashMap::new();
+    let mut attributes = vec![];
     let mut start_index = 0;
-    let mut node_char_range = start_index..start_index;
+    let mut node_char_range = 0..0;
     let mut state = StateMachine::ValueBlank;
     let source = token.value;
-    source.bytes().enumerate().for_each(|(i, c)| match state {
-        StateMachine::ValueBlank => {
-            if c.is_ascii_whitespace() {
-                start_index += 1;
-                return;
-            }
-            state = StateMachine::ValueChar
-        }
-        StateMachine::ValueChar => {
-            if c.is_ascii_whitespace() {
-                node_char_range = start_index..i;
-                state = StateMachine::EleKeyBlank
+    // the token value starts right after "<"
+    let value_start = token.span.start.advanced("<");
+    for (i, c) in source.bytes().enumerate() {
+        match state {
+            StateMachine::ValueBlank => {
+                if !(c.is_ascii_whitespace()) {
+                    start_index = i;
+                    state = StateMachine::ValueChar
+                }
             }
-        }
-        StateMachine::EleKeyBlank => {
-            if !(c.is_ascii_whitespace()) {
-                start_index = i;
-                state = StateMachine::EleKeyChar;
+            StateMachine::ValueChar => {
+                if c.is_ascii_whitespace() {
+                    node_char_range = start_index..i;
+                    state = StateMachine::EleKeyBlank
+                }
             }
-        }
-        StateMachine::EleKeyChar => {
-            if c.is_ascii_whitespace() {
-                key_range = start_index..i;
-                state = StateMachine::EleKeyBlank;
-                return;
+            StateMachine::EleKeyBlank => {
+                if !(c.is_ascii_whitespace()) {
+                    start_index = i;
+                    state = StateMachine::EleKeyChar;
+                }
             }
-            if c == b'=' {
-                key_range = start_index..i;
-                state = StateMachine::EleValBlank;
+            StateMachine::EleKeyChar => {
+                if c.is_ascii_whitespace() {
+                    key_range = start_index..i;
+                    state = StateMachine::EleKeyEnd;
+                    continue;
+                }
+                if c == b'=' {
+                    key_range = start_index..i;
+                    state = StateMachine::EleValBlank;
+                }
             }
-        }
-        StateMachine::EleValBlank => {
-            if c == b'"' {
-                start_index = i + 1;
-                state = StateMachine::EleValStart;
+            StateMachine::EleKeyEnd => {
+                if c == b'=' {
+                    state = StateMachine::EleValBlank;
+                } else if !(c.is_ascii_whitespace()) {
+                    attributes.push((key_range.clone(), None, vec![]));
+                    start_index = i;
+                    state = StateMachine::EleKeyChar;
+                }
             }
-        }
-        StateMachine::EleValStart => {
-            if !(c.is_ascii_whitespace()) {
-                start_index = i;
-                state = StateMachine::EleValChar;
+            StateMachine::EleValBlank => {
+                if c == b'"' || c == b'\'' {
+                    quote = c;
+                    start_index = i + 1;
+                    raw_start = i + 1;
+                    state = StateMachine::EleValStart;
+                } else if !(c.is_ascii_whitespace()) {
+                    if !options.unquoted_attribute_values {
+                        let start = value_start.advanced(&source[..i]);
+                        let first = source[i..].chars().next().map_or(1, char::len_utf8);
+                        let error = ParseError::UnquotedAttributeValue {
+                            name: source[key_range.clone()].to_string(),
+                            span: Span::new(start, start.advanced(&source[i..i + first])),
+                        };
+                        recover(diagnostics, error, Recovery::KeptAsWritten)?;
+                    }
+                    raw_start = i;
+                    state = StateMachine::EleValUnquoted;
+                }
             }
-        }
-        StateMachine::EleValChar => {
-            if c == b'"' {
-                value_range_list.push(start_index..i);
-                let mut v = vec![];
-                for range in &value_range_list {
-                    v.push(source.get(range.clone()).unwrap());
-                }
-                value_range_list = vec![];
-                property.insert(source.get(key_range.clone()).unwrap(), v);
-                state = StateMachine::EleKeyBlank;
-                return;
+            StateMachine::EleValStart => {
+                if c == quote {
+                    value_range_list.push(raw_start..i);
+                    attributes.push((
+                        key_range.clone(),
+                        Some(raw_start..i),
+                        std::mem::take(&mut value_range_list),
+                    ));
+                    state = StateMachine::EleKeyBlank;
+                    continue;
+                }
+                if !(c.is_ascii_whitespace()) {
+                    start_index = i;
+                    state = StateMachine::EleValChar;
+                }
             }
-            if c.is_ascii_whitespace() {
-                value_range_list.push(start_index..i);
-                state = StateMachine::EleValSplit;
+            StateMachine::EleValChar => {
+                if c == quote {
+                    value_range_list.push(start_index..i);
+                    attributes.push((
+                        key_range.clone(),
+                        Some(raw_start..i),
+                        std::mem::take(&mut value_range_list),
+                    ));
+                    state = StateMachine::EleKeyBlank;
+                    continue;
+                }
+                if c.is_ascii_whitespace() {
+                    value_range_list.push(start_index..i);
+                    state = StateMachine::EleValSplit;
+                }
             }
-        }
-        StateMachine::EleValSplit => {
-            if c == b'"' {
-                value_range_list.push(start_index..i);
-                state = StateMachine::EleKeyBlank;
+            StateMachine::EleValSplit => {
+                if c == quote {
+                    attributes.push((
+                        key_range.clone(),
+                        Some(raw_start..i),
+                        std::mem::take(&mut value_range_list),
+                    ));
+                    state = StateMachine::EleKeyBlank;
+                    continue;
+                }
+                if !(c.is_ascii_whitespace()) {
+                    start_index = i;
+                    state = StateMachine::EleValChar;
+                }
             }
-            if !(c.is_ascii_whitespace()) {
-                start_index = i;
-                state = StateMachine::EleValChar;
-                return;
+            StateMachine::EleValUnquoted => {
+                if c.is_ascii_whitespace() {
+                    attributes.push((key_range.clone(), Some(raw_start..i), vec![raw_start..i]));
+                    state = StateMachine::EleKeyBlank;
+                }
             }
         }
-    });
+    }
+    match state {
+        StateMachine::ValueChar => node_char_range = start_index..source.len(),
+        StateMachine::EleKeyChar => attributes.push((start_index..source.len(), None, vec![])),
+        StateMachine::EleKeyEnd => attributes.push((key_range, None, vec![])),
+        StateMachine::EleValUnquoted => attributes.push((
+            key_range,
+            Some(raw_start..source.len()),
+            vec![raw_start..source.len()],
+        )),
+        // `key=` at the end of the tag
+        StateMachine::EleValBlank if options.unquoted_attribute_values => {
+            attributes.push((key_range, None, vec![]))
+        }
+        StateMachine::EleValBlank => {
+            let key = &source[key_range.clone()];
+            let start = value_start.advanced(&source[..key_range.start]);
+            let error = ParseError::MissingAttributeValue {
+                name: key.to_string(),
+                span: Span::new(start, start.advanced(key)),
+            };
+            recover(diagnostics, error, Recovery::Skipped)?;
+        }
+        _ => (),
+    }
     let node_type = match token.token_type {
         TokenType::SingleElement => NodeType::Element,
         TokenType::Element => NodeType::Element,
         _ => panic!("not consider end and character type"),
     };
-    if start_index == 0 {
-        node_char_range = 0..(source.len())
-    }
-    if state == StateMachine::EleKeyChar {
-        let key = source.get(start_index..source.len()).unwrap();
-
-        property.insert(key, vec![""]);
+    let mut property = Attributes::new();
+    for (key_range, raw_range, value_range_list) in attributes {
+        let key = source.get(key_range.clone()).unwrap();
+        if property.contains_key(key) {
+            match options.duplicate_attributes {
+                DuplicateAttributePolicy::Error => {
+                    let start = value_start.advanced(&source[..key_range.start]);
+                    let error = ParseError::DuplicateAttribute {
+                        name: key.to_string(),
+                        span: Span::new(start, start.advanced(key)),
+                    };
+                    // the first value wins, like with `FirstWins`
+                    recover(diagnostics, error, Recovery::Skipped)?;
+                    continue;
+                }
+                DuplicateAttributePolicy::FirstWins => continue,
+                DuplicateAttributePolicy::LastWins => (),
+            }
+        }
+        let Some(raw_range) = raw_range else {
+            property.insert(Attribute::new(key, vec![Cow::Borrowed("")]));
+            continue;
+        };
+        let start = |range: &Range<usize>| value_start.advanced(&source[..range.start]);
+        let value = decode(
+            entities,
+            &source[raw_range.clone()],
+            start(&raw_range),
+            diagnostics,
+        )?;
+        let values = if options.is_tokenized(key) {
+            // the failed references were already recorded, and the
+            // expansions counted, with the whole value
+            let mut recorded = vec![];
+            let mut diagnostics = diagnostics.as_ref().map(|_| &mut recorded);
+            entities.uncounted(|| {
+                value_range_list
+                    .into_iter()
+                    .map(|range| {
+                        decode(
+                            entities,
+                            &source[range.clone()],
+                            start(&range),
+                            &mut diagnostics,
+                        )
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })?
+        } else {
+            vec![value.clone()]
+        };
+        property.insert(Attribute {
+            key,
+            raw: source.get(raw_range),
+            value,
+            values,
+            namespace_uri: None,
+        });
     }
     let node = QuickNode::with_property(
-        &source.get(node_char_range).unwrap(),
+        source.get(node_char_range).unwrap(),
         node_type,
         Some(property),
     );
 
-    node
+    Ok(node)
 }
 
 #[cfg(test)]
 mod token_to_node_tests {
 
-    use crate::xml::trees::nodes::node_interface::PropertyInterface;
+    use crate::xml::trees::nodes::node_interface::{ElementInterface, PropertyInterface};
 
     use super::*;
     #[test]
@@ -167,7 +567,10 @@ mod token_to_node_tests {
         node.add_property("class", "style");
         node.add_property("class", "style2");
         node.add_property("only", "");
-        assert_eq!(token.to_node(), node)
+        assert_eq!(
+            token.to_node(&ParseOptions::default(), &Entities::default()),
+            Ok(node)
+        )
     }
     #[test]
     fn token_to_node_case_element_test() {
@@ -176,17 +579,222 @@ mod token_to_node_tests {
         node.add_property("id", "kai");
         node.add_property("class", "style");
         node.add_property("class", "style2");
-        assert_eq!(token.to_node(), node)
+        assert_eq!(
+            token.to_node(&ParseOptions::default(), &Entities::default()),
+            Ok(node)
+        )
+    }
+    #[test]
+    fn token_to_node_case_reference_test() {
+        let token = Token::with_type(r#"a title="&lt;x&gt; &amp;y" id="1""#, TokenType::Element);
+        let node = token
+            .to_node(&ParseOptions::default(), &Entities::default())
+            .unwrap();
+        assert!(node.contains_key_value("title", "<x> &y"));
+        assert_eq!(node.get("title"), Some("<x> &y"));
+        assert_eq!(node.raw_property("title"), Some("&lt;x&gt; &amp;y"));
+        assert_eq!(node.raw_property("id"), Some("1"));
+        assert_eq!(node.raw_property("none"), None);
+
+        let token = Token::with_type("a &amp; b", TokenType::Text);
+        let node = token
+            .to_node(&ParseOptions::default(), &Entities::default())
+            .unwrap();
+        assert_eq!(node.value(), "a &amp; b");
+        assert_eq!(node.decoded_value(), "a & b");
+        let token = Token::with_type("a b", TokenType::Text);
+        let node = token
+            .to_node(&ParseOptions::default(), &Entities::default())
+            .unwrap();
+        // text without references is not copied
+        assert!(std::ptr::eq(node.decoded_value(), token.value));
+    }
+    #[test]
+    fn token_to_node_case_single_quote_test() {
+        let token = Token::with_type(
+            r#"a id='x' title='say "hi"' class = "b c" hidden"#,
+            TokenType::Element,
+        );
+        let mut node = QuickNode::new("a", NodeType::Element);
+        node.add_property("id", "x");
+        node.add_property("title", "say \"hi\"");
+        node.add_property("class", "b");
+        node.add_property("class", "c");
+        node.add_property("hidden", "");
+        assert_eq!(
+            token.to_node(&ParseOptions::default(), &Entities::default()),
+            Ok(node)
+        );
+    }
+    #[test]
+    fn token_to_node_case_unquoted_test() {
+        let token = Token::with_type(r#"img width=100 alt="x" height=50"#, TokenType::Element);
+        assert_eq!(
+            token.to_node(&ParseOptions::default(), &Entities::default()),
+            Err(ParseError::UnquotedAttributeValue {
+                name: "width".to_string(),
+                span: Span::new(
+                    Position {
+                        offset: 11,
+                        line: 1,
+                        column: 12
+                    },
+                    Position {
+                        offset: 12,
+                        line: 1,
+                        column: 13
+                    }
+                ),
+            })
+        );
+        let options = ParseOptions {
+            unquoted_attribute_values: true,
+            ..Default::default()
+        };
+        let mut node = QuickNode::new("img", NodeType::Element);
+        node.add_property("width", "100");
+        node.add_property("alt", "x");
+        node.add_property("height", "50");
+        let parsed = token.to_node(&options, &Entities::default()).unwrap();
+        assert_eq!(parsed, node);
+        assert_eq!(parsed.raw_property("height"), Some("50"));
+    }
+    #[test]
+    fn token_to_node_case_missing_value_test() {
+        let token = Token::with_type("a id='1' b=  ", TokenType::Element);
+        assert_eq!(
+            token.to_node(&ParseOptions::default(), &Entities::default()),
+            Err(ParseError::MissingAttributeValue {
+                name: "b".to_string(),
+                span: Span::new(
+                    Position {
+                        offset: 10,
+                        line: 1,
+                        column: 11
+                    },
+                    Position {
+                        offset: 11,
+                        line: 1,
+                        column: 12
+                    }
+                ),
+            })
+        );
+        let mut node = QuickNode::new("a", NodeType::Element);
+        node.add_property("id", "1");
+        node.add_property("b", "");
+        assert_eq!(
+            token.to_node(&ParseOptions::lenient(), &Entities::default()),
+            Ok(node)
+        );
+    }
+    #[test]
+    fn token_to_node_case_attribute_order_test() {
+        let token = Token::with_type(r#"a z="1" hidden b='x  y' m=" &amp; ""#, TokenType::Element);
+        let node = token
+            .to_node(&ParseOptions::default(), &Entities::default())
+            .unwrap();
+        assert_eq!(node.keys(), Some(vec!["z", "hidden", "b", "m"]));
+        assert_eq!(
+            node.values(),
+            Some(vec![
+                &vec![Cow::Borrowed("1")],
+                &vec![Cow::Borrowed("")],
+                &vec![Cow::Borrowed("x  y")],
+                &vec![Cow::Borrowed(" & ")],
+            ])
+        );
+        assert_eq!(node.raw_property("b"), Some("x  y"));
+        assert_eq!(node.raw_property("m"), Some(" &amp; "));
+        assert_eq!(node.raw_property("hidden"), None);
+    }
+    #[test]
+    fn token_to_node_case_tokenized_attribute_test() {
+        let token = Token::with_type(
+            r#"p class=" a  b " title="hello  world" data-x="1 2""#,
+            TokenType::Element,
+        );
+        let node = token
+            .to_node(&ParseOptions::default(), &Entities::default())
+            .unwrap();
+        assert_eq!(node.get("class"), Some(" a  b "));
+        assert_eq!(node.tokens("class"), Some(vec!["a", "b"]));
+        assert!(node.contains_key_value("class", "b"));
+        assert_eq!(node.get("title"), Some("hello  world"));
+        assert!(!node.contains_key_value("title", "hello"));
+        assert_eq!(node.tokens("title"), Some(vec!["hello", "world"]));
+        assert_eq!(node.get("none"), None);
+
+        let options = ParseOptions {
+            tokenized_attributes: vec!["data-x".to_string()],
+            ..Default::default()
+        };
+        let node = token.to_node(&options, &Entities::default()).unwrap();
+        assert!(node.contains_key_value("data-x", "2"));
+        assert!(!node.contains_key_value("class", "b"));
+        assert_eq!(node.get("data-x"), Some("1 2"));
+    }
+    #[test]
+    fn token_to_node_case_duplicate_attribute_test() {
+        let token = Token::with_type(r#"a id="1" x="y" id="2""#, TokenType::Element);
+        assert_eq!(
+            token.to_node(&ParseOptions::default(), &Entities::default()),
+            Err(ParseError::DuplicateAttribute {
+                name: "id".to_string(),
+                span: Span::new(
+                    Position {
+                        offset: 16,
+                        line: 1,
+                        column: 17
+                    },
+                    Position {
+                        offset: 18,
+                        line: 1,
+                        column: 19
+                    }
+                ),
+            })
+        );
+        let with_policy = |duplicate_attributes| ParseOptions {
+            duplicate_attributes,
+            ..Default::default()
+        };
+        let node = token
+            .to_node(
+                &with_policy(DuplicateAttributePolicy::FirstWins),
+                &Entities::default(),
+            )
+            .unwrap();
+        assert_eq!(node.get("id"), Some("1"));
+        let node = token
+            .to_node(
+                &with_policy(DuplicateAttributePolicy::LastWins),
+                &Entities::default(),
+            )
+            .unwrap();
+        assert_eq!(node.get("id"), Some("2"));
+        assert_eq!(node.keys(), Some(vec!["id", "x"]));
+        let token = Token::with_type("input checked checked", TokenType::SingleElement);
+        assert!(matches!(
+            token.to_node(&ParseOptions::default(), &Entities::default()),
+            Err(ParseError::DuplicateAttribute { .. })
+        ));
     }
     #[test]
     fn token_to_node_case_single_test() {
         let token = Token::with_type("div", TokenType::SingleElement);
-        assert_eq!(token.to_node(), QuickNode::new("div", NodeType::Element));
+        assert_eq!(
+            token.to_node(&ParseOptions::default(), &Entities::default()),
+            Ok(QuickNode::new("div", NodeType::Element))
+        );
     }
     #[test]
     fn token_to_node_case_charcter_test() {
         let token = Token::with_type("char", TokenType::Text);
-        assert_eq!(token.to_node(), QuickNode::new("char", NodeType::Text));
+        assert_eq!(
+            token.to_node(&ParseOptions::default(), &Entities::default()),
+            Ok(QuickNode::new("char", NodeType::Text))
+        );
     }
     #[test]
     fn token_to_node_case_workbook_test() {
@@ -233,6 +841,9 @@ mod token_to_node_tests {
             "http://schemas.microsoft.com/office/spreadsheetml/2015/revision2",
         );
 
-        assert_eq!(token.to_node(), node);
+        assert_eq!(
+            token.to_node(&ParseOptions::default(), &Entities::default()),
+            Ok(node)
+        );
     }
 }
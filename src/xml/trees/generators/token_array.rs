@@ -1,6 +1,22 @@
-use crate::xml::trees::{nodes::concreate_nodes::quick_node::QuickNode, tree::XmlTree};
+use crate::xml::trees::{
+    document::XmlDocument,
+    nodes::{
+        concreate_nodes::quick_node::QuickNode,
+        node_interface::{ElementInterface, PropertyInterface},
+    },
+    prolog::Prolog,
+    span::{LineIndex, Span},
+    tree::XmlTree,
+};
 
-use super::token::{Token, TokenType};
+use super::{
+    diagnostic::{note, recover, Diagnostic, Diagnostics, Recovery},
+    namespace::Namespaces,
+    parse_error::ParseError,
+    parse_options::{EndTagMode, ParseOptions, WhitespacePolicy},
+    reference::Entities,
+    token::{Token, TokenType},
+};
 enum StateMachine {
     CharBlank,
     CharChar,
@@ -8,53 +24,84 @@ enum StateMachine {
     EndChar,
     StartChar,
     StartSlash,
+    StartDoubleQuote,
+    StartSingleQuote,
+    Comment,
+    CData,
+    ProcessingInstruction,
+    Doctype,
+    DoctypeSubset,
+    DoctypeSubsetComment,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub(super) struct TokenArray<'a>(Vec<Token<'a>>);
 
 impl<'a> TokenArray<'a> {
-    pub fn new(source: &'a str) -> Self {
+    pub fn try_new(source: &'a str) -> Result<Self, ParseError> {
+        Self::new_with(source, &mut None)
+    }
+    /// Tokenize `source`, skipping a tag cut off by the end of the source.
+    pub fn recovering(source: &'a str, diagnostics: &mut Vec<Diagnostic>) -> Self {
+        Self::new_with(source, &mut Some(diagnostics)).expect("errors are recorded when recovering")
+    }
+    fn new_with(source: &'a str, diagnostics: &mut Diagnostics) -> Result<Self, ParseError> {
+        let index = LineIndex::new(source);
         let mut start_index = 0;
+        let mut tag_start = 0;
+        let mut slash_index = 0;
+        let mut quote = None;
+        // a quote only starts an attribute value right after '='
+        let mut after_equals = false;
+        let mut subset_comment_start = 0;
         let mut vec = Vec::new();
         let mut state = StateMachine::CharBlank;
         source.bytes().enumerate().for_each(|(i, c)| match state {
             StateMachine::CharBlank => match c {
-                60 => {
+                b'<' => {
                     state = StateMachine::StartStart;
+                    tag_start = i;
                     start_index = i + 1;
                 }
                 _ => {
-                    if !(c.is_ascii_whitespace()) {
-                        state = StateMachine::CharChar;
-                        start_index = i;
-                    }
+                    state = StateMachine::CharChar;
+                    start_index = i;
                 }
             },
-            StateMachine::CharChar => match c {
-                60 => {
-                    vec.push(Token::with_type(
+            // a text token is the whole run of character data up to the next tag
+            StateMachine::CharChar => {
+                if c == b'<' {
+                    vec.push(Token::with_span(
                         source.get(start_index..i).unwrap(),
                         TokenType::Text,
+                        index.span(start_index..i),
                     ));
                     state = StateMachine::StartStart;
+                    tag_start = i;
                     start_index = i + 1;
                 }
-                _ => {
-                    if c.is_ascii_whitespace() {
-                        vec.push(Token::with_type(
-                            source.get(start_index..i).unwrap(),
-                            TokenType::Text,
-                        ));
-                        state = StateMachine::CharBlank;
-                    }
-                }
-            },
+            }
             StateMachine::StartStart => match c {
-                47 => {
+                b'/' => {
                     state = StateMachine::EndChar;
                     start_index += 1;
                 }
+                b'!' if source[i..].starts_with("!--") => {
+                    state = StateMachine::Comment;
+                    start_index = i + 3;
+                }
+                b'!' if source[i..].starts_with("![CDATA[") => {
+                    state = StateMachine::CData;
+                    start_index = i + 8;
+                }
+                b'!' if source[i..].starts_with("!DOCTYPE") => {
+                    state = StateMachine::Doctype;
+                    start_index = i + 8;
+                }
+                b'?' => {
+                    state = StateMachine::ProcessingInstruction;
+                    start_index = i + 1;
+                }
                 _ => {
                     if c.is_ascii_whitespace() {
                         return;
@@ -62,43 +109,45 @@ impl<'a> TokenArray<'a> {
                     state = StateMachine::StartChar;
                 }
             },
-            StateMachine::EndChar => match c {
-                62 => {
-                    vec.push(Token::with_type(
-                        source.get(start_index..i).expect(
-                            format!(
-                                "len {} start {} i {}, range {:?}",
-                                source.len(),
-                                start_index,
-                                i,
-                                source.get(190..)
-                            )
-                            .as_str(),
-                        ),
+            StateMachine::EndChar => {
+                if c == b'>' {
+                    vec.push(Token::with_span(
+                        source.get(start_index..i).unwrap().trim(),
                         TokenType::EndElement,
+                        index.span(tag_start..i + 1),
                     ));
                     state = StateMachine::CharBlank;
                 }
-                _ => (),
-            },
+            }
             StateMachine::StartChar => match c {
-                47 => {
+                b'/' => {
+                    after_equals = false;
+                    slash_index = i;
                     state = StateMachine::StartSlash;
                 }
-                62 => {
+                b'=' => after_equals = true,
+                b'"' if after_equals => state = StateMachine::StartDoubleQuote,
+                b'\'' if after_equals => state = StateMachine::StartSingleQuote,
+                b'>' => {
                     state = StateMachine::CharBlank;
-                    vec.push(Token::with_type(
+                    vec.push(Token::with_span(
                         source.get(start_index..i).unwrap(),
                         TokenType::Element,
+                        index.span(tag_start..i + 1),
                     ))
                 }
-                _ => (),
+                _ => {
+                    if !(c.is_ascii_whitespace()) {
+                        after_equals = false;
+                    }
+                }
             },
             StateMachine::StartSlash => match c {
-                62 => {
-                    vec.push(Token::with_type(
-                        source.get(start_index..i - 1).unwrap(),
+                b'>' => {
+                    vec.push(Token::with_span(
+                        source.get(start_index..slash_index).unwrap(),
                         TokenType::SingleElement,
+                        index.span(tag_start..i + 1),
                     ));
                     state = StateMachine::CharBlank;
                 }
@@ -108,85 +157,461 @@ impl<'a> TokenArray<'a> {
                     }
                 }
             },
+            // '>' and '/' in attribute values do not end the tag
+            StateMachine::StartDoubleQuote => {
+                if c == b'"' {
+                    after_equals = false;
+                    state = StateMachine::StartChar;
+                }
+            }
+            StateMachine::StartSingleQuote => {
+                if c == b'\'' {
+                    after_equals = false;
+                    state = StateMachine::StartChar;
+                }
+            }
+            StateMachine::Comment => {
+                // the dashes of the opening "<!--" can not close the comment
+                if c == b'>' && i >= start_index + 2 && source[..i].ends_with("--") {
+                    vec.push(Token::with_span(
+                        source.get(start_index..i - 2).unwrap(),
+                        TokenType::Comment,
+                        index.span(tag_start..i + 1),
+                    ));
+                    state = StateMachine::CharBlank;
+                }
+            }
+            StateMachine::CData => {
+                if c == b'>' && i >= start_index + 2 && source[..i].ends_with("]]") {
+                    vec.push(Token::with_span(
+                        source.get(start_index..i - 2).unwrap(),
+                        TokenType::CData,
+                        index.span(tag_start..i + 1),
+                    ));
+                    state = StateMachine::CharBlank;
+                }
+            }
+            StateMachine::ProcessingInstruction => {
+                if c == b'>' && i > start_index && source[..i].ends_with('?') {
+                    let value = source.get(start_index..i - 1).unwrap();
+                    let token_type = if value == "xml"
+                        || value.starts_with("xml") && value[3..].starts_with(char::is_whitespace)
+                    {
+                        TokenType::Declaration
+                    } else {
+                        TokenType::ProcessingInstruction
+                    };
+                    vec.push(Token::with_span(
+                        value,
+                        token_type,
+                        index.span(tag_start..i + 1),
+                    ));
+                    state = StateMachine::CharBlank;
+                }
+            }
+            // quoted ids and the internal subset may hold '>'
+            StateMachine::Doctype => match (quote, c) {
+                (Some(q), _) if c == q => quote = None,
+                (Some(_), _) => (),
+                (None, b'"' | b'\'') => quote = Some(c),
+                (None, b'[') => state = StateMachine::DoctypeSubset,
+                (None, b'>') => {
+                    vec.push(Token::with_span(
+                        source.get(start_index..i).unwrap(),
+                        TokenType::Doctype,
+                        index.span(tag_start..i + 1),
+                    ));
+                    state = StateMachine::CharBlank;
+                }
+                _ => (),
+            },
+            StateMachine::DoctypeSubset => match (quote, c) {
+                (Some(q), _) if c == q => quote = None,
+                (Some(_), _) => (),
+                (None, b'"' | b'\'') => quote = Some(c),
+                (None, b'<') if source[i..].starts_with("<!--") => {
+                    subset_comment_start = i + 4;
+                    state = StateMachine::DoctypeSubsetComment
+                }
+                (None, b']') => state = StateMachine::Doctype,
+                _ => (),
+            },
+            // quotes in comments of the internal subset are not paired
+            StateMachine::DoctypeSubsetComment => {
+                if c == b'>' && i >= subset_comment_start + 2 && source[..i].ends_with("--") {
+                    state = StateMachine::DoctypeSubset;
+                }
+            }
         });
-
-        TokenArray(vec)
+        match state {
+            StateMachine::CharBlank => Ok(TokenArray(vec)),
+            StateMachine::CharChar => {
+                vec.push(Token::with_span(
+                    source.get(start_index..).unwrap(),
+                    TokenType::Text,
+                    index.span(start_index..source.len()),
+                ));
+                Ok(TokenArray(vec))
+            }
+            _ => {
+                let error = ParseError::UnexpectedEof {
+                    span: index.span(tag_start..source.len()),
+                };
+                recover(diagnostics, error, Recovery::Skipped)?;
+                Ok(TokenArray(vec))
+            }
+        }
+    }
+    pub fn tokens(&self) -> &[Token<'a>] {
+        &self.0
+    }
+    #[cfg(test)]
+    pub fn try_to_tree(
+        &self,
+        options: &ParseOptions,
+    ) -> Result<XmlTree<'a, QuickNode<'a>>, ParseError> {
+        self.try_to_document(options).map(|document| document.root)
+    }
+    #[cfg(test)]
+    pub fn try_to_document(&self, options: &ParseOptions) -> Result<XmlDocument<'a>, ParseError> {
+        let document = self.to_document(options, &mut None)?;
+        Ok(document.expect("a missing root is an error when not recovering"))
+    }
+    /// The best-effort document, `None` when the source has no element.
+    pub fn recover_document(
+        &self,
+        options: &ParseOptions,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Option<XmlDocument<'a>> {
+        self.to_document(options, &mut Some(diagnostics))
+            .expect("errors are recorded when recovering")
+    }
+    /// The document, `None` only when recovering from a source without
+    /// an element.
+    pub fn to_document(
+        &self,
+        options: &ParseOptions,
+        diagnostics: &mut Diagnostics,
+    ) -> Result<Option<XmlDocument<'a>>, ParseError> {
+        let (mut prolog, top_level) = self.to_top_level(options, false, diagnostics)?;
+        let mut top_level = top_level.into_iter();
+        let root = loop {
+            match top_level.next() {
+                Some((Some(_), root)) => break root,
+                Some((None, node)) => prolog.nodes.push(node),
+                None => {
+                    // a source that ended inside markup was reported already
+                    let truncated = diagnostics.as_deref().is_some_and(|diagnostics| {
+                        diagnostics.iter().any(|diagnostic| {
+                            matches!(diagnostic.error, ParseError::UnexpectedEof { .. })
+                        })
+                    });
+                    if !truncated {
+                        let error = ParseError::UnexpectedEof {
+                            span: self.0.last().map(|token| token.span).unwrap_or_default(),
+                        };
+                        recover(diagnostics, error, Recovery::Skipped)?;
+                    }
+                    return Ok(None);
+                }
+            }
+        };
+        let mut epilog = Vec::new();
+        for (element, node) in top_level {
+            if let Some((name, span)) = element {
+                let error = ParseError::MultipleRoots {
+                    name: name.to_string(),
+                    span,
+                };
+                recover(diagnostics, error, Recovery::Skipped)?;
+                continue;
+            }
+            epilog.push(node);
+        }
+        Ok(Some(XmlDocument {
+            prolog,
+            root,
+            epilog,
+        }))
     }
-    pub fn to_tree(self) -> XmlTree<'a, QuickNode<'a>> {
-        let mut parent_stack = Vec::new();
-        for token in self.0 {
+    /// Every top level node, text included, in source order.
+    pub fn to_fragment(
+        &self,
+        options: &ParseOptions,
+        diagnostics: &mut Diagnostics,
+    ) -> Result<Vec<XmlTree<'a, QuickNode<'a>>>, ParseError> {
+        let (_, top_level) = self.to_top_level(options, true, diagnostics)?;
+        Ok(top_level.into_iter().map(|(_, node)| node).collect())
+    }
+    /// The declarations and the top level nodes in source order. The nodes
+    /// of the returned prolog are left empty. Elements come with their name
+    /// and start tag. Top level text is only accepted in a fragment.
+    /// Errors are recorded in `diagnostics` when recovering.
+    #[allow(clippy::type_complexity)]
+    fn to_top_level(
+        &self,
+        options: &ParseOptions,
+        fragment: bool,
+        diagnostics: &mut Diagnostics,
+    ) -> Result<
+        (
+            Prolog<'a>,
+            Vec<(Option<(&'a str, Span)>, XmlTree<'a, QuickNode<'a>>)>,
+        ),
+        ParseError,
+    > {
+        let mut prolog = Prolog::default();
+        let mut entities = Entities::new(options);
+        let mut namespaces = Namespaces::new(options);
+        let mut top_level = Vec::new();
+        let mut parent_stack: Vec<XmlTree<'a, QuickNode<'a>>> = Vec::new();
+        // name, start tag and whether xml:space="preserve" is in effect
+        let mut open_tags: Vec<(&'a str, Span, bool)> = Vec::new();
+        for token in self.0.iter() {
             match token.token_type {
                 TokenType::Element => {
-                    let node = token.to_node();
+                    let mut node = token.to_node_with(options, &entities, diagnostics)?;
+                    namespaces.enter(&mut node, token.span, diagnostics)?;
+                    let preserve = if node.contains_key_value("xml:space", "preserve") {
+                        true
+                    } else if node.contains_key_value("xml:space", "default") {
+                        false
+                    } else {
+                        open_tags.last().is_some_and(|(_, _, preserve)| *preserve)
+                    };
+                    open_tags.push((node.value(), token.span, preserve));
                     parent_stack.push(XmlTree::new(node, None))
                 }
                 TokenType::EndElement => {
-                    let child = parent_stack.pop();
-                    match child {
-                        Some(node) => {
-                            if parent_stack.len() == 0 {
-                                return node;
+                    let found = open_tags
+                        .iter()
+                        .rposition(|(name, ..)| *name == token.value);
+                    let depth = match found {
+                        Some(depth) if depth + 1 == open_tags.len() => found,
+                        _ if options.end_tag_mode == EndTagMode::AutoClose => {
+                            match found {
+                                Some(depth) => {
+                                    for (name, span, _) in open_tags[depth + 1..].iter().rev() {
+                                        let error = ParseError::UnclosedTag {
+                                            name: name.to_string(),
+                                            span: *span,
+                                        };
+                                        note(diagnostics, error, Recovery::AutoClosed);
+                                    }
+                                }
+                                None => {
+                                    let error = ParseError::UnexpectedEndTag {
+                                        name: token.value.to_string(),
+                                        span: token.span,
+                                    };
+                                    note(diagnostics, error, Recovery::Skipped);
+                                }
                             }
-                            parent_stack.last_mut().unwrap().append_children(node)
+                            found
+                        }
+                        _ => {
+                            let error = match open_tags.last() {
+                                Some((expected, expected_span, _)) => {
+                                    ParseError::MismatchedEndTag {
+                                        expected: expected.to_string(),
+                                        expected_position: expected_span.start,
+                                        found: token.value.to_string(),
+                                        span: token.span,
+                                    }
+                                }
+                                None => ParseError::UnexpectedEndTag {
+                                    name: token.value.to_string(),
+                                    span: token.span,
+                                },
+                            };
+                            let recovery = match found {
+                                Some(_) => Recovery::AutoClosed,
+                                None => Recovery::Skipped,
+                            };
+                            recover(diagnostics, error, recovery)?;
+                            found
+                        }
+                    };
+                    let Some(depth) = depth else {
+                        continue;
+                    };
+                    while open_tags.len() > depth {
+                        let (name, span, _) = open_tags.pop().unwrap();
+                        namespaces.leave();
+                        if let Some(root) = Self::close_element(&mut parent_stack) {
+                            top_level.push((Some((name, span)), root));
+                        }
+                    }
+                }
+                TokenType::Declaration => {
+                    // not even whitespace may come before the declaration
+                    if token.span.start.offset != 0 {
+                        let error = ParseError::MisplacedDeclaration { span: token.span };
+                        recover(diagnostics, error, Recovery::Skipped)?;
+                        continue;
+                    }
+                    match token.to_declaration() {
+                        Ok(declaration) => prolog.declaration = Some(declaration),
+                        Err(error) => recover(diagnostics, error, Recovery::Skipped)?,
+                    }
+                }
+                TokenType::Doctype => {
+                    if prolog.doctype.is_some()
+                        || !parent_stack.is_empty()
+                        || top_level.iter().any(|(element, _)| element.is_some())
+                    {
+                        let error = ParseError::MisplacedDoctype { span: token.span };
+                        recover(diagnostics, error, Recovery::Skipped)?;
+                        continue;
+                    }
+                    let doctype = match token.to_doctype() {
+                        Ok(doctype) => doctype,
+                        Err(error) => {
+                            recover(diagnostics, error, Recovery::Skipped)?;
+                            continue;
+                        }
+                    };
+                    if let Some(subset) = doctype.internal_subset {
+                        if entities.declare(subset).is_none() {
+                            let error = ParseError::InvalidDoctype { span: token.span };
+                            recover(diagnostics, error, Recovery::Skipped)?;
+                            continue;
                         }
-                        None => panic!("error: this case is not parse"),
                     }
+                    prolog.doctype = Some(doctype);
+                    prolog.doctype_index = top_level.len();
                 }
+                TokenType::Text => {
+                    let whitespace = match open_tags.last() {
+                        Some((_, _, true)) => WhitespacePolicy::Preserve,
+                        _ => options.whitespace,
+                    };
+                    match parent_stack.last_mut() {
+                        Some(parent) => {
+                            if let Some(node) =
+                                token.to_text_node(whitespace, &entities, diagnostics)?
+                            {
+                                parent.append_children(XmlTree::new(node, None))
+                            }
+                        }
+                        None if token.is_blank() => {}
+                        None if fragment => {
+                            if let Some(node) =
+                                token.to_text_node(whitespace, &entities, diagnostics)?
+                            {
+                                top_level.push((None, XmlTree::new(node, None)))
+                            }
+                        }
+                        None => {
+                            let token = token.trimmed();
+                            let error = ParseError::TextOutsideRoot {
+                                text: token.value.to_string(),
+                                span: token.span,
+                            };
+                            recover(diagnostics, error, Recovery::Skipped)?;
+                        }
+                    }
+                }
+                TokenType::Comment if options.ignore_comments => {}
                 _ => {
-                    let node = token.to_node();
-                    parent_stack
-                        .last_mut()
-                        .unwrap()
-                        .append_children(XmlTree::new(node, None))
+                    let mut node = token.to_node_with(options, &entities, diagnostics)?;
+                    if token.token_type == TokenType::SingleElement {
+                        namespaces.enter(&mut node, token.span, diagnostics)?;
+                        namespaces.leave();
+                    }
+                    let name = node.value();
+                    let node = XmlTree::new(node, None);
+                    match parent_stack.last_mut() {
+                        Some(parent) => parent.append_children(node),
+                        None if token.token_type == TokenType::CData && !fragment => {
+                            let error = ParseError::TextOutsideRoot {
+                                text: token.value.to_string(),
+                                span: token.span,
+                            };
+                            recover(diagnostics, error, Recovery::Skipped)?;
+                        }
+                        None if token.token_type == TokenType::SingleElement => {
+                            top_level.push((Some((name, token.span)), node))
+                        }
+                        None => top_level.push((None, node)),
+                    }
                 }
             }
         }
-        // case exist declear line
-        if parent_stack.len() == 1 {
-            let single_parent = parent_stack.pop().unwrap();
-            return single_parent;
+        while let Some((name, span, _)) = open_tags.pop() {
+            let error = ParseError::UnclosedTag {
+                name: name.to_string(),
+                span,
+            };
+            if options.end_tag_mode == EndTagMode::AutoClose {
+                note(diagnostics, error, Recovery::AutoClosed);
+            } else {
+                recover(diagnostics, error, Recovery::AutoClosed)?;
+            }
+            if let Some(root) = Self::close_element(&mut parent_stack) {
+                top_level.push((Some((name, span)), root));
+            }
+        }
+        Ok((prolog, top_level))
+    }
+    /// Pop the innermost open element into its parent, returning it when it was the root.
+    fn close_element(
+        parent_stack: &mut Vec<XmlTree<'a, QuickNode<'a>>>,
+    ) -> Option<XmlTree<'a, QuickNode<'a>>> {
+        let child = parent_stack.pop()?;
+        match parent_stack.last_mut() {
+            Some(parent) => {
+                parent.append_children(child);
+                None
+            }
+            None => Some(child),
         }
-        panic!("not had end tag this stack : {:?}", parent_stack)
     }
 }
 
 #[cfg(test)]
 mod p_token_array_test {
-    use crate::xml::trees::nodes::{node_interface::PropertyInterface, node_type::NodeType};
+    use crate::xml::trees::{nodes::node_type::NodeType, prolog::XmlDeclaration};
 
     use super::*;
 
     #[test]
     fn build_test() {
+        // the text runs of the indented sources without the indentation
+        let significant = |source| -> Vec<Token> {
+            TokenArray::try_new(source)
+                .unwrap()
+                .0
+                .iter()
+                .filter(|token| !token.is_blank())
+                .map(|token| Token::with_type(token.value.trim(), token.token_type.clone()))
+                .collect()
+        };
         let source = r#"
                     <div>
                         hello world
                     </div>
                     "#;
-        let token_array = TokenArray::new(source);
         assert_eq!(
-            token_array,
-            TokenArray(vec![
+            significant(source),
+            vec![
                 Token::with_type("div", TokenType::Element),
-                Token::with_type("hello", TokenType::Text),
-                Token::with_type("world", TokenType::Text),
+                Token::with_type("hello world", TokenType::Text),
                 Token::with_type("div", TokenType::EndElement),
-            ])
+            ]
         );
         let source = r#"
         <div id="name" class="style style2">
         hello world
         </div>
         "#;
-        let token_array = TokenArray::new(source);
         assert_eq!(
-            token_array,
-            TokenArray(vec![
+            significant(source),
+            vec![
                 Token::with_type(r#"div id="name" class="style style2""#, TokenType::Element),
-                Token::with_type("hello", TokenType::Text),
-                Token::with_type("world", TokenType::Text),
+                Token::with_type("hello world", TokenType::Text),
                 Token::with_type("div", TokenType::EndElement),
-            ])
+            ]
         );
         let source = r#"
         <div id="name" class="style style2">
@@ -195,21 +620,176 @@ mod p_token_array_test {
         <p> p desu </ p>
         </div>
         "#;
-        let token_array = TokenArray::new(source);
+        assert_eq!(
+            significant(source),
+            vec![
+                Token::with_type(r#"div id="name" class="style style2""#, TokenType::Element),
+                Token::with_type("data", TokenType::SingleElement),
+                Token::with_type("hello world", TokenType::Text),
+                Token::with_type("p", TokenType::Element),
+                Token::with_type("p desu", TokenType::Text),
+                Token::with_type("p", TokenType::EndElement),
+                Token::with_type("div", TokenType::EndElement),
+            ]
+        );
+        let source = "\n<div>\n  hello world\n</div>\n";
+        let token_array = TokenArray::try_new(source).unwrap();
+        assert_eq!(
+            token_array,
+            TokenArray(vec![
+                Token::with_type("\n", TokenType::Text),
+                Token::with_type("div", TokenType::Element),
+                Token::with_type("\n  hello world\n", TokenType::Text),
+                Token::with_type("div", TokenType::EndElement),
+                Token::with_type("\n", TokenType::Text),
+            ])
+        );
+        let source = r#"<div id="name" class="style style2">hello  world</div>"#;
+        let token_array = TokenArray::try_new(source).unwrap();
         assert_eq!(
             token_array,
             TokenArray(vec![
                 Token::with_type(r#"div id="name" class="style style2""#, TokenType::Element),
+                Token::with_type("hello  world", TokenType::Text),
+                Token::with_type("div", TokenType::EndElement),
+            ])
+        );
+        let source = "<div><data />hello world <p> p desu </ p></div>";
+        let token_array = TokenArray::try_new(source).unwrap();
+        assert_eq!(
+            token_array,
+            TokenArray(vec![
+                Token::with_type("div", TokenType::Element),
                 Token::with_type("data ", TokenType::SingleElement),
-                Token::with_type("hello", TokenType::Text),
-                Token::with_type("world", TokenType::Text),
+                Token::with_type("hello world ", TokenType::Text),
                 Token::with_type("p", TokenType::Element),
-                Token::with_type("p", TokenType::Text),
-                Token::with_type("desu", TokenType::Text),
-                Token::with_type(" p", TokenType::EndElement),
+                Token::with_type(" p desu ", TokenType::Text),
+                Token::with_type("p", TokenType::EndElement),
+                Token::with_type("div", TokenType::EndElement),
+            ])
+        );
+    }
+    #[test]
+    fn build_quoted_attribute_test() {
+        let source = r#"<a href="http://x/y?a>b">link</a>"#;
+        assert_eq!(
+            TokenArray::try_new(source).unwrap(),
+            TokenArray(vec![
+                Token::with_type(r#"a href="http://x/y?a>b""#, TokenType::Element),
+                Token::with_type("link", TokenType::Text),
+                Token::with_type("a", TokenType::EndElement),
+            ])
+        );
+        let source = r#"<img src="a/b.png"/><img src='c/d.png' />"#;
+        assert_eq!(
+            TokenArray::try_new(source).unwrap(),
+            TokenArray(vec![
+                Token::with_type(r#"img src="a/b.png""#, TokenType::SingleElement),
+                Token::with_type("img src='c/d.png' ", TokenType::SingleElement),
+            ])
+        );
+        let source = r#"<xsl:value-of select="//book[price>35]/title"/>"#;
+        assert_eq!(
+            TokenArray::try_new(source).unwrap(),
+            TokenArray(vec![Token::with_type(
+                r#"xsl:value-of select="//book[price>35]/title""#,
+                TokenType::SingleElement
+            )])
+        );
+        let source = r#"<m expr='a/b > c' note="it's" label='say "x"'>1</m>"#;
+        assert_eq!(
+            TokenArray::try_new(source).unwrap(),
+            TokenArray(vec![
+                Token::with_type(
+                    r#"m expr='a/b > c' note="it's" label='say "x"'"#,
+                    TokenType::Element
+                ),
+                Token::with_type("1", TokenType::Text),
+                Token::with_type("m", TokenType::EndElement),
+            ])
+        );
+        assert!(matches!(
+            TokenArray::try_new(r#"<a href="x>"#),
+            Err(ParseError::UnexpectedEof { .. })
+        ));
+        // only a quote right after '=' starts a value
+        assert_eq!(
+            TokenArray::try_new("<a t=it's b = 'x>y'>x</a>").unwrap(),
+            TokenArray(vec![
+                Token::with_type("a t=it's b = 'x>y'", TokenType::Element),
+                Token::with_type("x", TokenType::Text),
+                Token::with_type("a", TokenType::EndElement),
+            ])
+        );
+    }
+    #[test]
+    fn build_comment_test() {
+        let source = r#"<div><!-- <p>commented > out</p> -->hello<!----></div>"#;
+        let token_array = TokenArray::try_new(source).unwrap();
+        assert_eq!(
+            token_array,
+            TokenArray(vec![
+                Token::with_type("div", TokenType::Element),
+                Token::with_type(" <p>commented > out</p> ", TokenType::Comment),
+                Token::with_type("hello", TokenType::Text),
+                Token::with_type("", TokenType::Comment),
                 Token::with_type("div", TokenType::EndElement),
             ])
         );
+        assert_eq!(token_array.0[1].span.range(), 5..36);
+        assert!(matches!(
+            TokenArray::try_new("<div><!-- -></div>"),
+            Err(ParseError::UnexpectedEof { .. })
+        ));
+        assert!(matches!(
+            TokenArray::try_new("<div><!--></div>"),
+            Err(ParseError::UnexpectedEof { .. })
+        ));
+    }
+    #[test]
+    fn build_cdata_test() {
+        let source = "<script><![CDATA[\n  if (a < b && c > d) { x = \"]]\" }\n]]></script>";
+        let token_array = TokenArray::try_new(source).unwrap();
+        assert_eq!(
+            token_array,
+            TokenArray(vec![
+                Token::with_type("script", TokenType::Element),
+                Token::with_type("\n  if (a < b && c > d) { x = \"]]\" }\n", TokenType::CData),
+                Token::with_type("script", TokenType::EndElement),
+            ])
+        );
+        assert!(matches!(
+            TokenArray::try_new("<a><![CDATA[x]></a>"),
+            Err(ParseError::UnexpectedEof { .. })
+        ));
+        assert!(matches!(
+            TokenArray::try_new("<![CDATA[x]]><a></a>")
+                .unwrap()
+                .try_to_tree(&ParseOptions::default()),
+            Err(ParseError::TextOutsideRoot { .. })
+        ));
+    }
+    #[test]
+    fn to_tree_comment_test() {
+        let data = "<!-- top --><div><!-- <p>old</p> -->data</div>";
+        let token_array = TokenArray::try_new(data).unwrap();
+        let mut div = XmlTree::new(QuickNode::new("div", NodeType::Element), None);
+        div.append_children(XmlTree::new(
+            QuickNode::new(" <p>old</p> ", NodeType::Comment),
+            None,
+        ));
+        div.append_children(XmlTree::new(QuickNode::new("data", NodeType::Text), None));
+        assert_eq!(
+            token_array.try_to_tree(&ParseOptions::default()).unwrap(),
+            div
+        );
+        let options = ParseOptions {
+            ignore_comments: true,
+            ..Default::default()
+        };
+        let mut div = XmlTree::new(QuickNode::new("div", NodeType::Element), None);
+        div.append_children(XmlTree::new(QuickNode::new("data", NodeType::Text), None));
+        assert_eq!(token_array.try_to_tree(&options).unwrap(), div);
     }
     #[test]
     fn to_trees_test() {
@@ -220,7 +800,10 @@ mod p_token_array_test {
         </div>
     </div>";
 
-        let expect = TokenArray::new(data).to_tree();
+        let expect = TokenArray::try_new(data)
+            .unwrap()
+            .try_to_tree(&ParseOptions::default())
+            .unwrap();
         let p = QuickNode::new("p", NodeType::Element);
         let mut p = XmlTree::new(p, None);
         let p_data = QuickNode::new("p-data", NodeType::Text);
@@ -248,13 +831,19 @@ mod p_token_array_test {
                     div-data
                 </div>
             </div>"#;
-        let expect = TokenArray::new(data).to_tree();
-        let mut root = QuickNode::new("?xml", NodeType::Element);
-        root.add_property("version", "1.0");
-        root.add_property("encoding", "UTF-8");
-        root.add_property("standalone", "yes");
-        root.add_property("?", "");
-        let mut root = XmlTree::new(root, None);
+        let (prolog, expect) = TokenArray::try_new(data)
+            .unwrap()
+            .try_to_document(&ParseOptions::default())
+            .map(|document| (document.prolog, document.root))
+            .unwrap();
+        assert_eq!(
+            prolog.declaration,
+            Some(XmlDeclaration {
+                version: "1.0",
+                encoding: Some("UTF-8"),
+                standalone: Some(true),
+            })
+        );
         let p = QuickNode::new("p", NodeType::Element);
         let mut p = XmlTree::new(p, None);
         let p_data = QuickNode::new("p-data", NodeType::Text);
@@ -275,7 +864,24 @@ mod p_token_array_test {
         child_div.append_children(single_data);
         child_div.append_children(div_data);
         div.append_children(child_div);
-        root.append_children(div);
-        assert_eq!(expect, root)
+        assert_eq!(expect, div)
+    }
+    #[test]
+    fn build_processing_instruction_test() {
+        let source = r#"<?xml version="1.0"?><?pi a>b ?><a/>"#;
+        let token_array = TokenArray::try_new(source).unwrap();
+        assert_eq!(
+            token_array,
+            TokenArray(vec![
+                Token::with_type(r#"xml version="1.0""#, TokenType::Declaration),
+                Token::with_type("pi a>b ", TokenType::ProcessingInstruction),
+                Token::with_type("a", TokenType::SingleElement),
+            ])
+        );
+        let token_array = TokenArray::try_new("<?xml-stylesheet?><a/>").unwrap();
+        assert_eq!(
+            token_array.0[0],
+            Token::with_type("xml-stylesheet", TokenType::ProcessingInstruction)
+        );
     }
 }
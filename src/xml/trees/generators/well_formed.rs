@@ -0,0 +1,281 @@
+use std::ops::Range;
+
+use crate::xml::trees::span::LineIndex;
+
+use super::{
+    parse_error::{ParseError, Violation},
+    token::{Token, TokenType},
+};
+
+/// Check the tokens of `source` against the well-formedness constraints
+/// that the tree builder lets through. Every violation is returned, in
+/// source order.
+pub(super) fn check(source: &str, tokens: &[Token]) -> Vec<ParseError> {
+    let mut checker = Checker {
+        index: LineIndex::new(source),
+        errors: vec![],
+    };
+    for (offset, c) in source.char_indices() {
+        if !is_char(c) {
+            checker.report(
+                Violation::IllegalCharacter(c),
+                offset..offset + c.len_utf8(),
+            );
+        }
+    }
+    for token in tokens {
+        let start = token.span.start.offset;
+        match token.token_type {
+            TokenType::Element | TokenType::SingleElement => {
+                checker.check_tag(token.value, start + "<".len())
+            }
+            TokenType::EndElement => {
+                // the token value is trimmed, so read the name from the source
+                let name = source[token.span.range()]
+                    .trim_start_matches("</")
+                    .trim_end_matches('>')
+                    .trim_end();
+                checker.check_name(name, start + "</".len());
+            }
+            TokenType::Text => {
+                if let Some(i) = token.value.find("]]>") {
+                    checker.report(Violation::CDataEndInText, start + i..start + i + 3);
+                }
+            }
+            TokenType::Comment => {
+                let start = start + "<!--".len();
+                if let Some(i) = token.value.find("--") {
+                    checker.report(Violation::DoubleHyphenInComment, start + i..start + i + 2);
+                } else if token.value.ends_with('-') {
+                    let end = start + token.value.len();
+                    checker.report(Violation::DoubleHyphenInComment, end - 1..end + 2);
+                }
+            }
+            TokenType::ProcessingInstruction => {
+                let start = start + "<?".len();
+                let target = token
+                    .value
+                    .split(|c: char| c.is_ascii_whitespace())
+                    .next()
+                    .unwrap_or_default();
+                if target.eq_ignore_ascii_case("xml") {
+                    let violation = Violation::ReservedTarget(target.to_string());
+                    checker.report(violation, start..start + target.len());
+                } else {
+                    checker.check_name(target, start);
+                }
+            }
+            TokenType::Doctype => {
+                let start = start + "<!DOCTYPE".len();
+                let name = token.value.trim_start();
+                let start = start + token.value.len() - name.len();
+                let end = name
+                    .find(|c: char| c.is_ascii_whitespace() || c == '[')
+                    .unwrap_or(name.len());
+                checker.check_name(&name[..end], start);
+            }
+            TokenType::CData | TokenType::Declaration => (),
+        }
+    }
+    checker
+        .errors
+        .sort_by_key(|error| error.span().start.offset);
+    checker.errors
+}
+
+struct Checker<'s> {
+    index: LineIndex<'s>,
+    errors: Vec<ParseError>,
+}
+
+impl Checker<'_> {
+    fn report(&mut self, violation: Violation, range: Range<usize>) {
+        let span = self.index.span(range);
+        self.errors
+            .push(ParseError::NotWellFormed { violation, span });
+    }
+    fn check_name(&mut self, name: &str, start: usize) {
+        if !is_name(name) {
+            self.report(
+                Violation::InvalidName(name.to_string()),
+                start..start + name.len(),
+            );
+        }
+    }
+    /// Check the name and the attributes of a start tag. `tag` starts
+    /// right after the `<` at `start`. Unquoted values are left to the
+    /// tree builder.
+    fn check_tag(&mut self, tag: &str, start: usize) {
+        let is_space = |c: char| c.is_ascii_whitespace();
+        let name_end = tag.find(is_space).unwrap_or(tag.len());
+        self.check_name(&tag[..name_end], start);
+        if name_end == 0 {
+            return;
+        }
+        let mut i = name_end;
+        loop {
+            let space_start = i;
+            i = skip_space(tag, i);
+            if i == tag.len() {
+                return;
+            }
+            if i == space_start {
+                self.report(Violation::MissingWhitespace, start + i..start + i);
+            }
+            let key_end = tag[i..]
+                .find(|c: char| is_space(c) || c == '=')
+                .map_or(tag.len(), |end| i + end);
+            let key = &tag[i..key_end];
+            self.check_name(key, start + i);
+            let equals = skip_space(tag, key_end);
+            if !tag[equals..].starts_with('=') {
+                let violation = Violation::MissingAttributeValue(key.to_string());
+                self.report(violation, start + i..start + key_end);
+                i = key_end;
+                continue;
+            }
+            let value_start = skip_space(tag, equals + 1);
+            let Some(quote) = tag[value_start..]
+                .chars()
+                .next()
+                .filter(|c| *c == '"' || *c == '\'')
+            else {
+                return;
+            };
+            let value_start = value_start + 1;
+            let Some(value_end) = tag[value_start..].find(quote).map(|end| value_start + end)
+            else {
+                return;
+            };
+            let value = &tag[value_start..value_end];
+            if let Some(lt) = value.find('<') {
+                let lt = start + value_start + lt;
+                let violation = Violation::LessThanInAttributeValue(key.to_string());
+                self.report(violation, lt..lt + 1);
+            }
+            i = value_end + 1;
+        }
+    }
+}
+
+fn skip_space(source: &str, from: usize) -> usize {
+    source[from..]
+        .find(|c: char| !c.is_ascii_whitespace())
+        .map_or(source.len(), |i| from + i)
+}
+
+/// The `Char` production of XML 1.0.
+pub(super) fn is_char(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r' | ' '..='\u{D7FF}' | '\u{E000}'..='\u{FFFD}' | '\u{10000}'..)
+}
+
+/// The `Name` production of XML 1.0.
+fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(is_name_start_char) && chars.all(is_name_char)
+}
+
+fn is_name_start_char(c: char) -> bool {
+    matches!(c,
+        ':' | 'A'..='Z' | '_' | 'a'..='z'
+        | '\u{C0}'..='\u{D6}'
+        | '\u{D8}'..='\u{F6}'
+        | '\u{F8}'..='\u{2FF}'
+        | '\u{370}'..='\u{37D}'
+        | '\u{37F}'..='\u{1FFF}'
+        | '\u{200C}'..='\u{200D}'
+        | '\u{2070}'..='\u{218F}'
+        | '\u{2C00}'..='\u{2FEF}'
+        | '\u{3001}'..='\u{D7FF}'
+        | '\u{F900}'..='\u{FDCF}'
+        | '\u{FDF0}'..='\u{FFFD}'
+        | '\u{10000}'..='\u{EFFFF}')
+}
+
+fn is_name_char(c: char) -> bool {
+    is_name_start_char(c)
+        || matches!(c,
+            '-' | '.' | '0'..='9' | '\u{B7}'
+            | '\u{300}'..='\u{36F}'
+            | '\u{203F}'..='\u{2040}')
+}
+
+#[cfg(test)]
+mod well_formed_tests {
+    use super::*;
+    use crate::xml::trees::generators::token_array::TokenArray;
+    fn violations(source: &str) -> Vec<(Violation, usize, usize)> {
+        let tokens = TokenArray::try_new(source).unwrap();
+        check(source, tokens.tokens())
+            .into_iter()
+            .map(|error| match error {
+                ParseError::NotWellFormed { violation, span } => {
+                    (violation, span.start.line, span.start.column)
+                }
+                _ => unreachable!(),
+            })
+            .collect()
+    }
+    #[test]
+    fn name_test() {
+        assert!(is_name("a:b-c.d_1"));
+        assert!(is_name("名前"));
+        assert!(!is_name("1a"));
+        assert!(!is_name("-a"));
+        assert!(!is_name(""));
+        assert_eq!(
+            violations("<1a x=\"1\"><b 2y='2'/></ 1a>"),
+            vec![
+                (Violation::InvalidName("1a".to_string()), 1, 2),
+                (Violation::InvalidName("2y".to_string()), 1, 14),
+                (Violation::InvalidName(" 1a".to_string()), 1, 24),
+            ]
+        );
+        assert_eq!(
+            violations("<?1pi?><!DOCTYPE 9 []><a/>"),
+            vec![
+                (Violation::InvalidName("1pi".to_string()), 1, 3),
+                (Violation::InvalidName("9".to_string()), 1, 18),
+            ]
+        );
+    }
+    #[test]
+    fn attribute_test() {
+        assert_eq!(
+            violations("<a x=\"1<2\" y='1'z='2' checked>\n</a>"),
+            vec![
+                (Violation::LessThanInAttributeValue("x".to_string()), 1, 8),
+                (Violation::MissingWhitespace, 1, 17),
+                (
+                    Violation::MissingAttributeValue("checked".to_string()),
+                    1,
+                    23
+                ),
+            ]
+        );
+        assert_eq!(violations("<a x = 'a' y=\"'<'\"/>").len(), 1);
+    }
+    #[test]
+    fn character_test() {
+        assert_eq!(
+            violations("<a>\u{1}\n&#65; \u{FFFF}</a>"),
+            vec![
+                (Violation::IllegalCharacter('\u{1}'), 1, 4),
+                (Violation::IllegalCharacter('\u{FFFF}'), 2, 7),
+            ]
+        );
+    }
+    #[test]
+    fn markup_test() {
+        assert_eq!(
+            violations("<a><!-- a -- b --><!-- c ---> ]]> <?XML x?></a>"),
+            vec![
+                (Violation::DoubleHyphenInComment, 1, 11),
+                (Violation::DoubleHyphenInComment, 1, 26),
+                (Violation::CDataEndInText, 1, 31),
+                (Violation::ReservedTarget("XML".to_string()), 1, 37),
+            ]
+        );
+        assert!(violations("<a><!-- ok --><![CDATA[]]]]><?xml-stylesheet x?></a>").is_empty());
+    }
+}
@@ -1,20 +1,166 @@
-use super::token_array::TokenArray;
-use crate::xml::trees::{nodes::concreate_nodes::quick_node::QuickNode, tree::XmlTree};
+use std::borrow::Cow;
+
+use super::{
+    diagnostic::{Diagnostic, Diagnostics, Recovery},
+    encoding,
+    parse_error::ParseError,
+    parse_options::ParseOptions,
+    token_array::TokenArray,
+    well_formed,
+};
+use crate::xml::trees::{
+    document::XmlDocument, nodes::concreate_nodes::quick_node::QuickNode, prolog::Prolog,
+    tree::XmlTree,
+};
 pub struct XmlGenerator;
 impl XmlGenerator {
     pub fn gen<'a>(source: &'a str) -> XmlTree<'a, QuickNode<'a>> {
-        let token_array = TokenArray::new(source);
-        token_array.to_tree()
+        Self::try_gen(source).unwrap_or_else(|e| panic!("{}", e))
+    }
+    pub fn gen_document<'a>(source: &'a str) -> XmlDocument<'a> {
+        Self::try_gen_document(source, &ParseOptions::default()).unwrap_or_else(|e| panic!("{}", e))
+    }
+    pub fn try_gen<'a>(source: &'a str) -> Result<XmlTree<'a, QuickNode<'a>>, ParseError> {
+        Self::try_gen_with_options(source, &ParseOptions::default())
+    }
+    pub fn try_gen_with_options<'a>(
+        source: &'a str,
+        options: &ParseOptions,
+    ) -> Result<XmlTree<'a, QuickNode<'a>>, ParseError> {
+        Self::build(source, options, |token_array, diagnostics| {
+            let document = token_array.to_document(options, diagnostics)?;
+            Ok(document.map(|document| document.root))
+        })
+    }
+    /// Parse the root element together with the xml declaration and the
+    /// comments and processing instructions in front of it.
+    pub fn try_gen_with_prolog<'a>(
+        source: &'a str,
+        options: &ParseOptions,
+    ) -> Result<(Prolog<'a>, XmlTree<'a, QuickNode<'a>>), ParseError> {
+        Self::try_gen_document(source, options).map(|document| (document.prolog, document.root))
+    }
+    /// Parse a whole document, keeping the comments and processing
+    /// instructions after the root. Another element after the root is an error.
+    pub fn try_gen_document<'a>(
+        source: &'a str,
+        options: &ParseOptions,
+    ) -> Result<XmlDocument<'a>, ParseError> {
+        Self::build(source, options, |token_array, diagnostics| {
+            token_array.to_document(options, diagnostics)
+        })
+    }
+    /// Decode a document from bytes in UTF-8, UTF-16, ISO-8859-1 or
+    /// windows-1252, to parse with the other functions. The encoding is
+    /// taken from the byte order mark or the xml declaration.
+    pub fn decode_bytes(bytes: &[u8]) -> Result<Cow<'_, str>, ParseError> {
+        encoding::decode_bytes(bytes)
+    }
+    pub fn gen_bytes<'a>(bytes: &'a [u8], buffer: &'a mut String) -> XmlTree<'a, QuickNode<'a>> {
+        Self::try_gen_bytes(bytes, buffer).unwrap_or_else(|e| panic!("{}", e))
+    }
+    pub fn try_gen_bytes<'a>(
+        bytes: &'a [u8],
+        buffer: &'a mut String,
+    ) -> Result<XmlTree<'a, QuickNode<'a>>, ParseError> {
+        Self::try_gen_bytes_with_options(bytes, buffer, &ParseOptions::default())
+    }
+    /// Decode like `decode_bytes` and parse the result. UTF-8 is parsed
+    /// in place, other encodings are decoded into `buffer`, and the tree
+    /// borrows from it.
+    pub fn try_gen_bytes_with_options<'a>(
+        bytes: &'a [u8],
+        buffer: &'a mut String,
+        options: &ParseOptions,
+    ) -> Result<XmlTree<'a, QuickNode<'a>>, ParseError> {
+        let source = match Self::decode_bytes(bytes)? {
+            Cow::Borrowed(source) => source,
+            Cow::Owned(decoded) => {
+                *buffer = decoded;
+                buffer.as_str()
+            }
+        };
+        Self::try_gen_with_options(source, options)
+    }
+    /// Parse a source with any number of top level elements, like a log
+    /// file with one element per line.
+    pub fn parse_fragment<'a>(
+        source: &'a str,
+    ) -> Result<Vec<XmlTree<'a, QuickNode<'a>>>, ParseError> {
+        Self::parse_fragment_with_options(source, &ParseOptions::default())
+    }
+    pub fn parse_fragment_with_options<'a>(
+        source: &'a str,
+        options: &ParseOptions,
+    ) -> Result<Vec<XmlTree<'a, QuickNode<'a>>>, ParseError> {
+        Self::build(source, options, |token_array, diagnostics| {
+            token_array.to_fragment(options, diagnostics).map(Some)
+        })
+    }
+    /// Parse without failing, for editors and linters. Each problem is
+    /// repaired as its diagnostic describes and the diagnostics are in
+    /// source order. The tree is `None` only when there is no element.
+    pub fn gen_recovering<'a>(
+        source: &'a str,
+        options: &ParseOptions,
+    ) -> (Option<XmlTree<'a, QuickNode<'a>>>, Vec<Diagnostic>) {
+        let mut diagnostics = Vec::new();
+        let token_array = TokenArray::recovering(source, &mut diagnostics);
+        if options.check_well_formedness {
+            diagnostics.extend(
+                well_formed::check(source, token_array.tokens())
+                    .into_iter()
+                    .map(|error| Diagnostic::new(error, Recovery::KeptAsWritten)),
+            );
+        }
+        let document = token_array.recover_document(options, &mut diagnostics);
+        diagnostics.sort_by_key(|diagnostic| diagnostic.span().start.offset);
+        (document.map(|document| document.root), diagnostics)
+    }
+    /// Tokenize `source` and run `build` on the tokens, which returns
+    /// `None` only when recovering. With `check_well_formedness` the
+    /// tokens and the tree are built recovering, and every error is
+    /// returned together with the violations.
+    fn build<'a, T>(
+        source: &'a str,
+        options: &ParseOptions,
+        build: impl FnOnce(&TokenArray<'a>, &mut Diagnostics) -> Result<Option<T>, ParseError>,
+    ) -> Result<T, ParseError> {
+        if !options.check_well_formedness {
+            let built = build(&TokenArray::try_new(source)?, &mut None)?;
+            return Ok(built.expect("a missing root is an error when not recovering"));
+        }
+        let mut diagnostics = Vec::new();
+        let token_array = TokenArray::recovering(source, &mut diagnostics);
+        let mut errors = well_formed::check(source, token_array.tokens());
+        let built = build(&token_array, &mut Some(&mut diagnostics))
+            .expect("errors are recorded when recovering");
+        errors.extend(diagnostics.into_iter().map(|diagnostic| diagnostic.error));
+        match built {
+            Some(built) if errors.is_empty() => Ok(built),
+            _ => {
+                errors.sort_by_key(|error| error.span().start.offset);
+                Err(ParseError::Multiple(errors))
+            }
+        }
     }
 }
 #[cfg(test)]
 mod xml_generator_tests {
-    use crate::xml::trees::generators::xml_generator::XmlGenerator;
-    use crate::xml::trees::nodes::node_interface::PropertyInterface;
+    use crate::xml::trees::generators::{
+        diagnostic::{Diagnostic, Recovery},
+        parse_error::ParseError,
+        parse_options::{ParseOptions, WhitespacePolicy},
+        xml_generator::XmlGenerator,
+    };
+    use crate::xml::trees::nodes::node_interface::{NodeInterface, PropertyInterface};
     use crate::xml::trees::{
         nodes::{concreate_nodes::quick_node::QuickNode, node_type::NodeType},
+        prolog::{Doctype, XmlDeclaration},
+        span::{Position, Span},
         tree::XmlTree,
     };
+    use std::borrow::Cow;
 
     #[test]
     fn gen_test() {
@@ -34,4 +180,855 @@ mod xml_generator_tests {
         let div_tree = XmlTree::new(div, Some(Box::new(vec![data_node])));
         assert_eq!(tree, div_tree)
     }
+    #[test]
+    fn try_gen_test() {
+        let source = "<div>data</div>";
+        let data_node = XmlTree::new(QuickNode::new("data", NodeType::Text), None);
+        let div_tree = XmlTree::new(
+            QuickNode::new("div", NodeType::Element),
+            Some(Box::new(vec![data_node])),
+        );
+        assert_eq!(XmlGenerator::try_gen(source), Ok(div_tree));
+        assert_eq!(
+            XmlGenerator::try_gen("<div><p>data</p>"),
+            Err(ParseError::UnclosedTag {
+                name: "div".to_string(),
+                span: line_span(0, 5)
+            })
+        );
+        assert_eq!(
+            XmlGenerator::try_gen("</div>"),
+            Err(ParseError::UnexpectedEndTag {
+                name: "div".to_string(),
+                span: line_span(0, 6)
+            })
+        );
+        assert_eq!(
+            XmlGenerator::try_gen("data<div></div>"),
+            Err(ParseError::TextOutsideRoot {
+                text: "data".to_string(),
+                span: line_span(0, 4)
+            })
+        );
+        assert_eq!(
+            XmlGenerator::try_gen("<div>data</div"),
+            Err(ParseError::UnexpectedEof {
+                span: line_span(9, 14)
+            })
+        );
+        assert_eq!(
+            XmlGenerator::try_gen(""),
+            Err(ParseError::UnexpectedEof {
+                span: Span::default()
+            })
+        );
+    }
+    #[test]
+    fn error_position_test() {
+        let error = XmlGenerator::try_gen("\n\n  oops<div></div>").unwrap_err();
+        assert_eq!(
+            error.span().start,
+            Position {
+                offset: 4,
+                line: 3,
+                column: 3
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            r#"text "oops" is outside of the root element at line 3, column 3"#
+        );
+    }
+    #[test]
+    fn node_span_test() {
+        let tree = XmlGenerator::gen("<div>\n  <p id=\"x\">data</p>\n</div>");
+        assert_eq!(tree.span(), Some(line_span(0, 5)));
+        let p = tree.get_elements_by_node_value("p")[0];
+        let span = p.span().unwrap();
+        assert_eq!(span.range(), 8..18);
+        assert_eq!((span.start.line, span.start.column), (2, 3));
+        let data = p.get_elements_by_node_value("data")[0];
+        assert_eq!(data.span().unwrap().range(), 18..22);
+    }
+    #[test]
+    fn gen_cdata_test() {
+        let tree = XmlGenerator::gen("<sql>select<![CDATA[ * from t where a < 1 & b ]]></sql>");
+        assert_eq!(
+            tree.text_contents(),
+            Some(vec!["select", " * from t where a < 1 & b "])
+        );
+        assert_eq!(tree.concat_all_text(), "select * from t where a < 1 & b ");
+        let tree = XmlGenerator::gen("<p>foo<b>bar</b>baz</p>");
+        assert_eq!(tree.concat_all_text(), "foobarbaz");
+    }
+    #[test]
+    fn gen_with_prolog_test() {
+        let source = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<?xml-stylesheet type="text/xsl" href="style.xsl"?>
+<!-- generated -->
+<root><?php echo 1; ?></root>"#;
+        let (prolog, tree) =
+            XmlGenerator::try_gen_with_prolog(source, &ParseOptions::default()).unwrap();
+        assert_eq!(
+            prolog.declaration,
+            Some(XmlDeclaration {
+                version: "1.0",
+                encoding: Some("UTF-8"),
+                standalone: Some(true),
+            })
+        );
+        assert_eq!(
+            prolog.nodes,
+            vec![
+                XmlTree::new(
+                    QuickNode::processing_instruction(
+                        "xml-stylesheet",
+                        r#"type="text/xsl" href="style.xsl""#
+                    ),
+                    None
+                ),
+                XmlTree::new(QuickNode::new(" generated ", NodeType::Comment), None),
+            ]
+        );
+        let php = XmlTree::new(QuickNode::processing_instruction("php", "echo 1; "), None);
+        let root = XmlTree::new(
+            QuickNode::new("root", NodeType::Element),
+            Some(Box::new(vec![php])),
+        );
+        assert_eq!(tree, root);
+        assert_eq!(root.concat_all_text(), "");
+    }
+    #[test]
+    fn invalid_declaration_test() {
+        assert!(matches!(
+            XmlGenerator::try_gen(r#"<?xml encoding="UTF-8"?><a/>"#),
+            Err(ParseError::InvalidDeclaration { .. })
+        ));
+        assert!(matches!(
+            XmlGenerator::try_gen(r#"<?xml version="1.0" standalone="maybe"?><a/>"#),
+            Err(ParseError::InvalidDeclaration { .. })
+        ));
+        assert!(matches!(
+            XmlGenerator::try_gen(r#"<?xml version="1.0"encoding="UTF-8"?><a/>"#),
+            Err(ParseError::InvalidDeclaration { .. })
+        ));
+        assert!(matches!(
+            XmlGenerator::try_gen(r#"<!-- c --><?xml version="1.0"?><a/>"#),
+            Err(ParseError::MisplacedDeclaration { .. })
+        ));
+        for source in [
+            "\n<?xml version=\"1.0\"?><a/>",
+            " <?xml version=\"1.0\"?><a/>",
+        ] {
+            assert!(matches!(
+                XmlGenerator::try_gen(source),
+                Err(ParseError::MisplacedDeclaration { .. })
+            ));
+            assert!(XmlGenerator::try_gen_with_options(source, &ParseOptions::strict()).is_err());
+        }
+        let (prolog, _) = XmlGenerator::try_gen_with_prolog(
+            "<?xml version='1.1'?><a/>",
+            &ParseOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(prolog.declaration, Some(XmlDeclaration::new("1.1")));
+    }
+    #[test]
+    fn gen_reference_test() {
+        let tree = XmlGenerator::gen(r#"<a title="x &amp; y">&lt;b&gt;&#x41;&#66;</a>"#);
+        assert_eq!(tree.text_contents(), Some(vec!["<b>AB"]));
+        assert_eq!(tree.get_elements_by_key_value("title", "x & y").len(), 1);
+        assert_eq!(
+            tree.get_elements_by_node_value("&lt;b&gt;&#x41;&#66;")
+                .len(),
+            1
+        );
+        let error =
+            XmlGenerator::try_gen("<a>Tom & Jerry go to the market and buy bread; then home</a>")
+                .unwrap_err();
+        assert_eq!(
+            error,
+            ParseError::InvalidReference {
+                reference: "&".to_string(),
+                span: line_span(7, 8)
+            }
+        );
+        let error = XmlGenerator::try_gen("<a>\n  x &nbsp; y</a>").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            r#"reference "&nbsp;" is invalid at line 2, column 5"#
+        );
+        let error = XmlGenerator::try_gen("<a>&#1;</a>").unwrap_err();
+        assert_eq!(
+            error,
+            ParseError::InvalidReference {
+                reference: "&#1;".to_string(),
+                span: line_span(3, 7)
+            }
+        );
+        let error = XmlGenerator::try_gen(r#"<a b="1 & 2"/>"#).unwrap_err();
+        assert_eq!(
+            error,
+            ParseError::InvalidReference {
+                reference: "&".to_string(),
+                span: line_span(8, 9)
+            }
+        );
+    }
+    #[test]
+    fn gen_attribute_quote_test() {
+        let tree = XmlGenerator::gen(r#"<feed><item id='1'/><item id="2"/></feed>"#);
+        assert_eq!(tree.get_elements_by_key_value("id", "1").len(), 1);
+        assert_eq!(tree.get_elements_by_key_value("id", "2").len(), 1);
+        assert!(matches!(
+            XmlGenerator::try_gen("<td width=100>x</td>"),
+            Err(ParseError::UnquotedAttributeValue { .. })
+        ));
+        let tree =
+            XmlGenerator::try_gen_with_options("<td width=100>x</td>", &ParseOptions::lenient())
+                .unwrap();
+        assert_eq!(tree.get_elements_by_key_value("width", "100").len(), 1);
+        let tree = XmlGenerator::try_gen_with_options("<a t=it's>x</a>", &ParseOptions::lenient())
+            .unwrap();
+        assert_eq!(tree.get_elements_by_key_value("t", "it's").len(), 1);
+        assert_eq!(
+            XmlGenerator::try_gen("<a x=é/>"),
+            Err(ParseError::UnquotedAttributeValue {
+                name: "x".to_string(),
+                span: Span::new(
+                    line_span(5, 5).start,
+                    Position {
+                        offset: 7,
+                        line: 1,
+                        column: 7
+                    }
+                )
+            })
+        );
+        assert_eq!(
+            XmlGenerator::try_gen("<a b=></a>").unwrap_err().to_string(),
+            "attribute b has no value at line 1, column 4"
+        );
+        assert!(matches!(
+            XmlGenerator::try_gen("<a b= c/>"),
+            Err(ParseError::UnquotedAttributeValue { .. })
+        ));
+        let (tree, diagnostics) = XmlGenerator::gen_recovering("<a b=/>", &ParseOptions::default());
+        assert_eq!(tree.unwrap().to_xml_string(), "<a/>");
+        assert_eq!(diagnostics[0].recovery, Recovery::Skipped);
+        let (tree, diagnostics) =
+            XmlGenerator::gen_recovering("<a x=é/>", &ParseOptions::default());
+        assert_eq!(tree.unwrap().get_elements_by_key_value("x", "é").len(), 1);
+        assert_eq!(diagnostics.len(), 1);
+    }
+    #[test]
+    fn gen_quoted_markup_attribute_test() {
+        let tree = XmlGenerator::gen(
+            r#"<p><a href="http://x/y?a>b">link</a><img src="a/b.png"/><m expr='a/b > c'/></p>"#,
+        );
+        assert_eq!(
+            tree.get_elements_by_key_value("href", "http://x/y?a>b")
+                .len(),
+            1
+        );
+        assert_eq!(tree.get_elements_by_key_value("src", "a/b.png").len(), 1);
+        assert_eq!(tree.get_elements_by_key_value("expr", "a/b > c").len(), 1);
+        assert_eq!(tree.text_contents(), Some(vec![]));
+    }
+    #[test]
+    fn mismatched_end_tag_test() {
+        assert_eq!(
+            XmlGenerator::try_gen("<a><b></a></b>"),
+            Err(ParseError::MismatchedEndTag {
+                expected: "b".to_string(),
+                expected_position: line_span(3, 6).start,
+                found: "a".to_string(),
+                span: line_span(6, 10),
+            })
+        );
+        let error = XmlGenerator::try_gen("<a>\n  <b>\n</a>").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "end tag </a> does not match open tag <b> from line 2, column 3 at line 3, column 1"
+        );
+        let tree = XmlGenerator::gen("<p>data</ p >");
+        let p = XmlTree::new(
+            QuickNode::new("p", NodeType::Element),
+            Some(Box::new(vec![XmlTree::new(
+                QuickNode::new("data", NodeType::Text),
+                None,
+            )])),
+        );
+        assert_eq!(tree, p);
+    }
+    #[test]
+    fn auto_close_end_tag_test() {
+        let options = ParseOptions::lenient();
+        let b = XmlTree::new(QuickNode::new("b", NodeType::Element), None);
+        let a = XmlTree::new(
+            QuickNode::new("a", NodeType::Element),
+            Some(Box::new(vec![b])),
+        );
+        assert_eq!(
+            XmlGenerator::try_gen_with_options("<a><b></a></b>", &options),
+            Ok(a.clone())
+        );
+        assert_eq!(
+            XmlGenerator::try_gen_with_options("<a><b></c></b></a>", &options),
+            Ok(a.clone())
+        );
+        assert_eq!(
+            XmlGenerator::try_gen_with_options("<a><b>", &options),
+            Ok(a)
+        );
+        let recoveries = |source| {
+            let (_, diagnostics) = XmlGenerator::gen_recovering(source, &options);
+            diagnostics
+                .iter()
+                .map(|diagnostic| diagnostic.recovery)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(recoveries("<a><b></a>"), vec![Recovery::AutoClosed]);
+        assert_eq!(
+            recoveries("<a><b><c></a>"),
+            vec![Recovery::AutoClosed, Recovery::AutoClosed]
+        );
+        assert_eq!(
+            recoveries("<a><b></a></b>"),
+            vec![Recovery::AutoClosed, Recovery::Skipped]
+        );
+        assert_eq!(
+            recoveries("<a><b>"),
+            vec![Recovery::AutoClosed, Recovery::AutoClosed]
+        );
+        assert_eq!(recoveries("<a><b></b></a>"), vec![]);
+        let (_, diagnostics) = XmlGenerator::gen_recovering("<a><b></a>", &options);
+        assert!(matches!(
+            &diagnostics[0].error,
+            ParseError::UnclosedTag { name, .. } if name == "b"
+        ));
+    }
+    #[test]
+    fn whitespace_policy_test() {
+        let source = "<a>\n  <b>  hello   world </b>\n</a>";
+        let element = |name, children: Vec<_>| {
+            XmlTree::new(
+                QuickNode::new(name, NodeType::Element),
+                Some(Box::new(children)),
+            )
+        };
+        let text = |raw, decoded| XmlTree::new(QuickNode::text(raw, Cow::Borrowed(decoded)), None);
+        let gen = |whitespace| {
+            let options = ParseOptions {
+                whitespace,
+                ..Default::default()
+            };
+            XmlGenerator::try_gen_with_options(source, &options)
+        };
+        assert_eq!(
+            gen(WhitespacePolicy::Trim),
+            Ok(element(
+                "a",
+                vec![element("b", vec![text("hello   world", "hello   world")])]
+            ))
+        );
+        assert_eq!(
+            gen(WhitespacePolicy::Collapse),
+            Ok(element(
+                "a",
+                vec![element("b", vec![text("hello   world", "hello world")])]
+            ))
+        );
+        assert_eq!(
+            gen(WhitespacePolicy::DropWhitespaceOnly),
+            Ok(element(
+                "a",
+                vec![element(
+                    "b",
+                    vec![text("  hello   world ", "  hello   world ")]
+                )]
+            ))
+        );
+        assert_eq!(
+            gen(WhitespacePolicy::Preserve),
+            Ok(element(
+                "a",
+                vec![
+                    text("\n  ", "\n  "),
+                    element("b", vec![text("  hello   world ", "  hello   world ")]),
+                    text("\n", "\n"),
+                ]
+            ))
+        );
+    }
+    #[test]
+    fn xml_space_test() {
+        let source = r#"<a xml:space="preserve"> x <b xml:space="default"> y </b><c> z </c></a>"#;
+        let tree = XmlGenerator::try_gen_with_options(
+            source,
+            &ParseOptions {
+                whitespace: WhitespacePolicy::Collapse,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let text_of = |name| tree.get_elements_by_node_value(name)[0].text_contents();
+        assert_eq!(tree.text_contents(), Some(vec![" x "]));
+        assert_eq!(text_of("b"), Some(vec!["y"]));
+        assert_eq!(text_of("c"), Some(vec![" z "]));
+    }
+    #[test]
+    fn gen_document_test() {
+        let source = "<!-- head --><a>x</a>\n<!-- tail --><?pi data?>\n";
+        let document = XmlGenerator::try_gen_document(source, &ParseOptions::default()).unwrap();
+        let comment = |value| XmlTree::new(QuickNode::new(value, NodeType::Comment), None);
+        assert_eq!(document.prolog.nodes, vec![comment(" head ")]);
+        assert_eq!(document.root.text_contents(), Some(vec!["x"]));
+        assert_eq!(
+            document.epilog,
+            vec![
+                comment(" tail "),
+                XmlTree::new(QuickNode::processing_instruction("pi", "data"), None)
+            ]
+        );
+        assert_eq!(
+            XmlGenerator::try_gen("<a>x</a>\n<b/>"),
+            Err(ParseError::MultipleRoots {
+                name: "b".to_string(),
+                span: Span::new(
+                    Position {
+                        offset: 9,
+                        line: 2,
+                        column: 1
+                    },
+                    Position {
+                        offset: 13,
+                        line: 2,
+                        column: 5
+                    }
+                ),
+            })
+        );
+        assert!(matches!(
+            XmlGenerator::try_gen("<a/><b></b>"),
+            Err(ParseError::MultipleRoots { .. })
+        ));
+        assert!(matches!(
+            XmlGenerator::try_gen("<a></a> tail"),
+            Err(ParseError::TextOutsideRoot { .. })
+        ));
+        assert!(matches!(
+            XmlGenerator::try_gen("<!-- only -->"),
+            Err(ParseError::UnexpectedEof { .. })
+        ));
+    }
+    #[test]
+    fn gen_doctype_test() {
+        let source = r#"<?xml version="1.0"?>
+<!-- before -->
+<!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.0 Strict//EN" "http://www.w3.org/TR/xhtml1/DTD/xhtml1-strict.dtd">
+<html/>"#;
+        let document = XmlGenerator::gen_document(source);
+        assert_eq!(
+            document.prolog.doctype,
+            Some(Doctype {
+                name: "html",
+                public_id: Some("-//W3C//DTD XHTML 1.0 Strict//EN"),
+                system_id: Some("http://www.w3.org/TR/xhtml1/DTD/xhtml1-strict.dtd"),
+                internal_subset: None,
+            })
+        );
+        assert_eq!(document.prolog.nodes.len(), 1);
+        let source = "<!DOCTYPE note SYSTEM 'note.dtd' [\n  <!ENTITY w \"<b>x</b>\">\n  <!-- don't -->\n]>\n<note/>";
+        let document = XmlGenerator::gen_document(source);
+        assert_eq!(
+            document.prolog.doctype,
+            Some(Doctype {
+                name: "note",
+                public_id: None,
+                system_id: Some("note.dtd"),
+                internal_subset: Some("\n  <!ENTITY w \"<b>x</b>\">\n  <!-- don't -->\n"),
+            })
+        );
+        assert_eq!(
+            XmlGenerator::try_gen("<!DOCTYPE a><!DOCTYPE a><a/>"),
+            Err(ParseError::MisplacedDoctype {
+                span: line_span(12, 24)
+            })
+        );
+        assert!(matches!(
+            XmlGenerator::try_gen("<a/><!DOCTYPE a>"),
+            Err(ParseError::MisplacedDoctype { .. })
+        ));
+        assert!(matches!(
+            XmlGenerator::try_gen("<!DOCTYPE a PUBLIC 'x'><a/>"),
+            Err(ParseError::InvalidDoctype { .. })
+        ));
+        assert!(matches!(
+            XmlGenerator::try_gen("<!DOCTYPE a [<!ENTITY x 'y'>"),
+            Err(ParseError::UnexpectedEof { .. })
+        ));
+    }
+    #[test]
+    fn gen_entity_test() {
+        let source = r#"<!DOCTYPE note [
+  <!ENTITY company "Acme &amp; Co">
+  <!ENTITY sign "-- &company;">
+]>
+<note by="&company;">Thanks &sign;</note>"#;
+        let tree = XmlGenerator::gen(source);
+        assert_eq!(tree.text_contents(), Some(vec!["Thanks -- Acme & Co"]));
+        assert_eq!(tree.get_elements_by_key_value("by", "Acme & Co").len(), 1);
+        assert!(matches!(
+            XmlGenerator::try_gen("<note>&company;</note>"),
+            Err(ParseError::InvalidReference { .. })
+        ));
+        let lol = r#"<!DOCTYPE lolz [
+ <!ENTITY lol "lol">
+ <!ENTITY lol1 "&lol;&lol;&lol;&lol;&lol;&lol;&lol;&lol;&lol;&lol;">
+ <!ENTITY lol2 "&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;">
+ <!ENTITY lol3 "&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;">
+ <!ENTITY lol4 "&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;">
+ <!ENTITY lol5 "&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;">
+ <!ENTITY lol6 "&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;">
+ <!ENTITY lol7 "&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;">
+ <!ENTITY lol8 "&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;">
+ <!ENTITY lol9 "&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;">
+]>
+<lolz>&lol9;</lolz>"#;
+        let error = XmlGenerator::try_gen(lol).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            r#"reference "&lol9;" expands beyond the entity limits at line 13, column 7"#
+        );
+        let options = ParseOptions {
+            entity_depth_limit: 1,
+            ..Default::default()
+        };
+        assert!(matches!(
+            XmlGenerator::try_gen_with_options(source, &options),
+            Err(ParseError::EntityLimitExceeded { .. })
+        ));
+        assert!(matches!(
+            XmlGenerator::try_gen(r#"<!DOCTYPE a [<!ENTITY a "&a;">]><a>&a;</a>"#),
+            Err(ParseError::RecursiveEntity { .. })
+        ));
+        // a tokenized value counts once against the limit
+        let options = ParseOptions {
+            entity_expansion_limit: 20,
+            ..Default::default()
+        };
+        let source = r#"<!DOCTYPE a [<!ENTITY e "0123456789">]><a class="&e; &e;"/>"#;
+        let tree = XmlGenerator::try_gen_with_options(source, &options).unwrap();
+        assert_eq!(tree.node().get("class"), Some("0123456789 0123456789"));
+        let source = r#"<!DOCTYPE a [<!ENTITY e "0123456789">]><a class="&e; &e; &e;"/>"#;
+        assert!(matches!(
+            XmlGenerator::try_gen_with_options(source, &options),
+            Err(ParseError::EntityLimitExceeded { .. })
+        ));
+    }
+    #[test]
+    fn gen_empty_prefix_test() {
+        let error = XmlGenerator::try_gen(r#"<a xmlns:="urn:x"/>"#).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "namespace declaration xmlns: has no prefix at line 1, column 1"
+        );
+    }
+    #[test]
+    fn gen_namespace_test() {
+        let main = "http://schemas.openxmlformats.org/spreadsheetml/2006/main";
+        let r = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+        let source = format!(
+            r#"<workbook xmlns="{}" xmlns:r="{}">
+  <sheets><sheet name="a" r:id="rId1"/></sheets>
+  <x:ext xmlns:x="urn:x" xml:lang="en"><x:sheet/><plain xmlns=""/></x:ext>
+</workbook>"#,
+            main, r
+        );
+        let tree = XmlGenerator::gen(&source);
+        let sheets = tree.get_elements_by_ns_name(main, "sheet");
+        assert_eq!(sheets.len(), 1);
+        let x_sheets = tree.get_elements_by_ns_name("urn:x", "sheet");
+        assert_eq!(x_sheets.len(), 1);
+        assert_eq!(tree.get_elements_by_ns_name("urn:x", "ext").len(), 1);
+        assert_eq!(tree.get_elements_by_ns_name(main, "plain").len(), 0);
+        assert_eq!(tree.get_elements_by_node_value("plain").len(), 1);
+        let element = tree.get_elements_by_key_value("r:id", "rId1");
+        assert_eq!(element.len(), 1);
+        let envelope = XmlGenerator::try_gen(r#"<soap:Envelope p:id="1"/>"#).unwrap();
+        assert_eq!(envelope.node().local_name(), "Envelope");
+        assert_eq!(envelope.node().namespace_uri(), None);
+        assert_eq!(
+            XmlGenerator::try_gen_with_options("<a><b:c/></a>", &ParseOptions::strict()),
+            Err(ParseError::Multiple(vec![ParseError::UnboundPrefix {
+                prefix: "b".to_string(),
+                span: line_span(3, 9)
+            }]))
+        );
+        let strict = ParseOptions::strict();
+        for source in [r#"<a><b xmlns:p="urn:p"/><p:c/></a>"#, r#"<a p:id="1"/>"#] {
+            assert!(XmlGenerator::try_gen(source).is_ok());
+            assert!(matches!(
+                XmlGenerator::try_gen_with_options(source, &strict),
+                Err(ParseError::Multiple(errors))
+                    if matches!(errors[..], [ParseError::UnboundPrefix { .. }])
+            ));
+        }
+    }
+    #[test]
+    fn decode_bytes_test() {
+        let source = "<?xml version=\"1.0\" encoding=\"UTF-16\"?><a title=\"é\">𝄞</a>";
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend(source.encode_utf16().flat_map(u16::to_le_bytes));
+        let decoded = XmlGenerator::decode_bytes(&bytes).unwrap();
+        assert_eq!(decoded, source);
+        let document = XmlGenerator::gen_document(&decoded);
+        assert_eq!(
+            document.prolog.declaration.as_ref().unwrap().encoding,
+            Some("UTF-16")
+        );
+        let decoded = XmlGenerator::decode_bytes(b"\xEF\xBB\xBF<a x='1'>hi</a>").unwrap();
+        assert!(matches!(decoded, Cow::Borrowed("<a x='1'>hi</a>")));
+        assert!(matches!(
+            XmlGenerator::decode_bytes(b"<?xml version=\"1.0\" encoding=\"EBCDIC\"?><a/>"),
+            Err(ParseError::UnsupportedEncoding { .. })
+        ));
+    }
+    #[test]
+    fn gen_bytes_test() {
+        let source = "<?xml version=\"1.0\" encoding=\"UTF-16\"?><a title=\"é\">𝄞</a>";
+        let mut buffer = String::new();
+        let le = [0xFF, 0xFE]
+            .into_iter()
+            .chain(source.encode_utf16().flat_map(u16::to_le_bytes))
+            .collect::<Vec<_>>();
+        let tree = XmlGenerator::gen_bytes(&le, &mut buffer);
+        assert_eq!(tree.text_contents(), Some(vec!["𝄞"]));
+        assert_eq!(tree.get_elements_by_key_value("title", "é").len(), 1);
+        let mut other = String::new();
+        let be = [0xFE, 0xFF]
+            .into_iter()
+            .chain(source.encode_utf16().flat_map(u16::to_be_bytes))
+            .collect::<Vec<_>>();
+        assert_eq!(XmlGenerator::gen_bytes(&be, &mut other), tree);
+
+        let mut buffer = String::new();
+        let tree = XmlGenerator::gen_bytes(b"\xEF\xBB\xBF<a x='1'>hi</a>", &mut buffer);
+        assert_eq!(tree.node().get("x"), Some("1"));
+        assert!(buffer.is_empty());
+        let mut buffer = String::new();
+        let latin1 = b"<?xml version=\"1.0\" encoding=\"latin1\"?><a>\xE9</a>";
+        let tree = XmlGenerator::gen_bytes(latin1, &mut buffer);
+        assert_eq!(tree.text_contents(), Some(vec!["é"]));
+
+        let mut buffer = String::new();
+        assert!(matches!(
+            XmlGenerator::try_gen_bytes(b"<a>", &mut buffer),
+            Err(ParseError::UnclosedTag { .. })
+        ));
+        let mut buffer = String::new();
+        assert!(matches!(
+            XmlGenerator::try_gen_bytes_with_options(
+                b"\xFF\xFE<\0a\0 \0x\0=\0y\0/\0>\0",
+                &mut buffer,
+                &ParseOptions::strict()
+            ),
+            Err(ParseError::Multiple(_))
+        ));
+    }
+    #[test]
+    fn mutate_and_serialize_test() {
+        let mut tree = XmlGenerator::gen(r#"<a class="x  y" id='1' lang='en' title="a &amp; b"/>"#);
+        let node = tree.node_mut();
+        node.add_property("class", "z");
+        node.set_property("id", "2");
+        assert!(node.remove_property_value("class", "x"));
+        assert!(node.rename_property("lang", "xml:lang"));
+        assert_eq!(node.get("class"), Some("y z"));
+        assert_eq!(
+            tree.to_xml_string(),
+            r#"<a class="y z" id="2" xml:lang="en" title="a &amp; b"/>"#
+        );
+        let mut tree = XmlGenerator::gen(r#"<a class="x y"/>"#);
+        tree.node_mut().add_property("class", "z");
+        assert_eq!(tree.to_xml_string(), r#"<a class="x y z"/>"#);
+        let mut tree = XmlGenerator::gen(r#"<a><b class="x"/><c><b/></c></a>"#);
+        let b = tree.find_by_name_mut("b").unwrap();
+        b.node_mut().add_property("class", "y");
+        tree.last_child_mut().unwrap().remove_child(0);
+        assert_eq!(tree.to_xml_string(), r#"<a><b class="x y"/><c/></a>"#);
+    }
+    #[test]
+    fn document_round_trip_test() {
+        let source = r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>
+<!DOCTYPE a SYSTEM "a.dtd" [<!ENTITY e "x">]>
+<!-- head -->
+<a id='say "hi"' class="b  c" hidden="">
+  text &amp; more<b/><![CDATA[<raw>]]><?pi some data?><c><!-- in --></c>
+</a>
+<?tail?>"#;
+        let document = XmlGenerator::gen_document(source);
+        let xml = document.to_xml_string();
+        assert_eq!(
+            xml,
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>
+<!DOCTYPE a SYSTEM "a.dtd" [<!ENTITY e "x">]>
+<!-- head -->
+<a id='say "hi"' class="b  c" hidden="">text &amp; more<b/><![CDATA[<raw>]]><?pi some data?><c><!-- in --></c></a>
+<?tail?>"#
+        );
+        assert_eq!(XmlGenerator::gen_document(&xml), document);
+        let source = "<!-- c -->\n<?pi?>\n<!DOCTYPE a>\n<!-- d -->\n<a/>";
+        let document = XmlGenerator::gen_document(source);
+        assert_eq!(document.prolog.doctype_index, 2);
+        assert_eq!(document.to_xml_string(), source);
+        let mut p = QuickNode::new("p", NodeType::Element);
+        p.add_property("title", r#"a<"b"&"#);
+        assert_eq!(
+            XmlTree::new(p, None).to_xml_string(),
+            r#"<p title="a&lt;&quot;b&quot;&amp;"/>"#
+        );
+    }
+    #[test]
+    fn parse_fragment_test() {
+        let source = "<log id=\"1\">start</log>\n<log id=\"2\"/>\nbye <!-- c -->";
+        let fragment = XmlGenerator::parse_fragment(source).unwrap();
+        assert_eq!(fragment.len(), 4);
+        assert_eq!(fragment[0].text_contents(), Some(vec!["start"]));
+        assert_eq!(fragment[1].get_elements_by_key_value("id", "2").len(), 1);
+        assert_eq!(
+            fragment[2],
+            XmlTree::new(QuickNode::new("bye", NodeType::Text), None)
+        );
+        assert_eq!(
+            fragment[3],
+            XmlTree::new(QuickNode::new(" c ", NodeType::Comment), None)
+        );
+        assert_eq!(XmlGenerator::parse_fragment(""), Ok(vec![]));
+        assert!(matches!(
+            XmlGenerator::parse_fragment("<a></a><b>"),
+            Err(ParseError::UnclosedTag { .. })
+        ));
+    }
+    #[test]
+    fn recovering_test() {
+        let source = "<a x=1 y='&bad;'>\n  <b>one & two</c>\n  <d>3</b>\n  </e>";
+        assert!(XmlGenerator::try_gen(source).is_err());
+        let (tree, diagnostics) = XmlGenerator::gen_recovering(source, &ParseOptions::default());
+        let tree = tree.unwrap();
+        assert_eq!(tree.get_elements_by_key_value("x", "1").len(), 1);
+        assert_eq!(tree.get_elements_by_key_value("y", "&bad;").len(), 1);
+        let b = tree.get_elements_by_node_value("b")[0];
+        assert_eq!(b.text_contents(), Some(vec!["one & two"]));
+        assert_eq!(b.get_elements_by_node_value("d").len(), 1);
+        let messages: Vec<_> = diagnostics.iter().map(Diagnostic::to_string).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "tag <a> is not closed at line 1, column 1 (closed automatically)",
+                "value of attribute x is not quoted at line 1, column 6 (kept as written)",
+                "reference \"&bad;\" is invalid at line 1, column 11 (kept as written)",
+                "reference \"&\" is invalid at line 2, column 10 (kept as written)",
+                "end tag </c> does not match open tag <b> from line 2, column 3 at line 2, column 15 (skipped)",
+                "end tag </b> does not match open tag <d> from line 3, column 3 at line 3, column 7 (closed automatically)",
+                "end tag </e> does not match open tag <a> from line 1, column 1 at line 4, column 3 (skipped)",
+            ]
+        );
+        let (tree, diagnostics) =
+            XmlGenerator::gen_recovering("<a><b></a>text<c/><d", &ParseOptions::default());
+        assert_eq!(tree.unwrap().get_elements_by_node_value("b").len(), 1);
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|diagnostic| (diagnostic.recovery, diagnostic.span().start.offset))
+                .collect::<Vec<_>>(),
+            vec![
+                (Recovery::AutoClosed, 6),
+                (Recovery::Skipped, 10),
+                (Recovery::Skipped, 14),
+                (Recovery::Skipped, 18),
+            ]
+        );
+        let (tree, diagnostics) = XmlGenerator::gen_recovering("", &ParseOptions::default());
+        assert_eq!(tree, None);
+        assert!(matches!(
+            diagnostics[..],
+            [Diagnostic {
+                error: ParseError::UnexpectedEof { .. },
+                recovery: Recovery::Skipped
+            }]
+        ));
+        let (_, diagnostics) = XmlGenerator::gen_recovering("<1a/>", &ParseOptions::strict());
+        assert_eq!(diagnostics.len(), 1);
+        let valid = "<a><b>x</b></a>";
+        assert_eq!(
+            XmlGenerator::gen_recovering(valid, &ParseOptions::default()),
+            (Some(XmlGenerator::gen(valid)), vec![])
+        );
+    }
+    #[test]
+    fn strict_test() {
+        let source = "<a x='1<2'>\n  <!-- a -- b -->\n  <1b/>\n</c>";
+        assert!(XmlGenerator::try_gen(source).is_err());
+        let error =
+            XmlGenerator::try_gen_with_options(source, &ParseOptions::strict()).unwrap_err();
+        let ParseError::Multiple(errors) = &error else {
+            panic!("{:?}", error)
+        };
+        assert_eq!(errors.len(), 5);
+        assert!(matches!(errors[4], ParseError::MismatchedEndTag { .. }));
+        assert_eq!(
+            error.to_string(),
+            "tag <a> is not closed at line 1, column 1
+value of attribute x contains '<' at line 1, column 8
+comment contains \"--\" at line 2, column 10
+\"1b\" is not a valid name at line 3, column 4
+end tag </c> does not match open tag <a> from line 1, column 1 at line 4, column 1"
+        );
+        let source = "<a><b>&nbsp;</c><d>&foo;</e></a>";
+        let error = XmlGenerator::try_gen_document(source, &ParseOptions::strict()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "reference \"&nbsp;\" is invalid at line 1, column 7
+end tag </c> does not match open tag <b> from line 1, column 4 at line 1, column 13
+reference \"&foo;\" is invalid at line 1, column 20
+end tag </e> does not match open tag <d> from line 1, column 17 at line 1, column 25
+end tag </a> does not match open tag <d> from line 1, column 17 at line 1, column 29"
+        );
+        let source = "<?xml version=\"1.0\"?>\n<a b='&#x1;'>\u{7}</a>";
+        assert!(XmlGenerator::try_gen(source.replace("&#x1;", "").as_str()).is_ok());
+        assert_eq!(
+            XmlGenerator::try_gen_document(source, &ParseOptions::strict())
+                .unwrap_err()
+                .to_string(),
+            "reference \"&#x1;\" is invalid at line 2, column 7
+character U+0007 is not allowed at line 2, column 14"
+        );
+        assert_eq!(
+            XmlGenerator::try_gen_with_options("<a", &ParseOptions::strict()),
+            Err(ParseError::Multiple(vec![ParseError::UnexpectedEof {
+                span: line_span(0, 2)
+            }]))
+        );
+        let valid = "<a:b xmlns:a='urn:a' c=\"&lt;\"><!-- ok --><?pi x?>text</a:b>";
+        assert_eq!(
+            XmlGenerator::try_gen_with_options(valid, &ParseOptions::strict()),
+            XmlGenerator::try_gen(valid)
+        );
+    }
+    fn line_span(start: usize, end: usize) -> Span {
+        Span::new(
+            Position {
+                offset: start,
+                line: 1,
+                column: start + 1,
+            },
+            Position {
+                offset: end,
+                line: 1,
+                column: end + 1,
+            },
+        )
+    }
 }
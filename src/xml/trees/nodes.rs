@@ -1,3 +1,4 @@
-pub(super) mod concreate_nodes;
-pub(super) mod node_interface;
-pub(super) mod node_type;
+pub mod attributes;
+pub mod concreate_nodes;
+pub mod node_interface;
+pub mod node_type;
@@ -0,0 +1,285 @@
+use std::borrow::Cow;
+
+use super::node_interface::{PropertyKey, PropertyValue};
+
+/// One attribute of an element.
+#[derive(Clone, Debug, Eq)]
+pub struct Attribute<'a> {
+    pub key: PropertyKey<'a>,
+    /// The value as written between the quotes in the source,
+    /// `None` for attributes added by hand.
+    pub raw: Option<&'a str>,
+    /// The whole value with references decoded.
+    pub value: Cow<'a, str>,
+    /// The value split on whitespace for token list attributes like `class`,
+    /// otherwise the whole value as the only item.
+    pub values: PropertyValue<'a>,
+    /// The namespace the prefix of the key resolves to.
+    pub namespace_uri: Option<Cow<'a, str>>,
+}
+
+// like spans, the raw text only records how the attribute was written,
+// and `value` only differs from `values` in the spacing between tokens
+impl PartialEq for Attribute<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+            && self.values == other.values
+            && self.namespace_uri == other.namespace_uri
+    }
+}
+
+impl<'a> Attribute<'a> {
+    pub fn new(key: PropertyKey<'a>, values: PropertyValue<'a>) -> Self {
+        Attribute {
+            key,
+            raw: None,
+            value: joined(&values),
+            values,
+            namespace_uri: None,
+        }
+    }
+    /// An attribute whose value is not split into tokens.
+    pub fn literal(key: PropertyKey<'a>, value: Cow<'a, str>) -> Self {
+        Attribute {
+            key,
+            raw: None,
+            values: vec![value.clone()],
+            value,
+            namespace_uri: None,
+        }
+    }
+    pub fn with_raw(mut self, raw: &'a str) -> Self {
+        self.raw = Some(raw);
+        self
+    }
+    /// The part of the key before `:`.
+    pub fn prefix(&self) -> Option<&'a str> {
+        self.key.split_once(':').map(|(prefix, _)| prefix)
+    }
+    /// The key without its prefix.
+    pub fn local_name(&self) -> &'a str {
+        match self.key.split_once(':') {
+            Some((_, local_name)) => local_name,
+            None => self.key,
+        }
+    }
+}
+
+/// The attributes of an element, kept in the order they were written.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Attributes<'a>(Vec<Attribute<'a>>);
+
+impl<'a> Attributes<'a> {
+    pub fn new() -> Self {
+        Attributes(Vec::new())
+    }
+    pub fn get(&self, key: &str) -> Option<&Attribute<'a>> {
+        self.0.iter().find(|attribute| attribute.key == key)
+    }
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Attribute<'a>> {
+        self.0.iter_mut().find(|attribute| attribute.key == key)
+    }
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+    /// Replace the attribute with the same key in place, or append it.
+    pub fn insert(&mut self, attribute: Attribute<'a>) {
+        match self.get_mut(attribute.key) {
+            Some(old) => *old = attribute,
+            None => self.0.push(attribute),
+        }
+    }
+    /// Append `value` to the values of `key`, adding the key when it is new.
+    pub fn push_value(&mut self, key: PropertyKey<'a>, value: Cow<'a, str>) {
+        match self.get_mut(key) {
+            Some(attribute) => {
+                attribute.values.push(value);
+                attribute.value = joined(&attribute.values);
+                // the source text no longer says what the value is
+                attribute.raw = None;
+            }
+            None => self.0.push(Attribute::literal(key, value)),
+        }
+    }
+    pub fn remove(&mut self, key: &str) -> Option<Attribute<'a>> {
+        let index = self.0.iter().position(|attribute| attribute.key == key)?;
+        Some(self.0.remove(index))
+    }
+    /// Remove one of the values of `key`, keeping the key even when no
+    /// value is left. Returns whether the value was there.
+    pub fn remove_value(&mut self, key: &str, value: &str) -> bool {
+        let Some(attribute) = self.get_mut(key) else {
+            return false;
+        };
+        let len = attribute.values.len();
+        attribute.values.retain(|v| v != value);
+        if attribute.values.len() == len {
+            return false;
+        }
+        attribute.value = joined(&attribute.values);
+        attribute.raw = None;
+        true
+    }
+    /// Give the attribute `from` the key `to` in place, replacing an
+    /// attribute already named `to`. Returns whether `from` was there.
+    pub fn rename(&mut self, from: &str, to: PropertyKey<'a>) -> bool {
+        if !self.contains_key(from) {
+            return false;
+        }
+        if from != to {
+            self.remove(to);
+        }
+        let attribute = self.get_mut(from).unwrap();
+        if attribute.prefix() != to.split_once(':').map(|(prefix, _)| prefix) {
+            // the new prefix may be bound to another namespace
+            attribute.namespace_uri = None;
+        }
+        attribute.key = to;
+        true
+    }
+    pub fn iter(&self) -> std::slice::Iter<'_, Attribute<'a>> {
+        self.0.iter()
+    }
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, Attribute<'a>> {
+        self.0.iter_mut()
+    }
+    pub fn keys(&self) -> impl Iterator<Item = PropertyKey<'a>> + '_ {
+        self.0.iter().map(|attribute| attribute.key)
+    }
+    pub fn values(&self) -> impl Iterator<Item = &PropertyValue<'a>> {
+        self.0.iter().map(|attribute| &attribute.values)
+    }
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+fn joined<'a>(values: &PropertyValue<'a>) -> Cow<'a, str> {
+    match values.as_slice() {
+        [] => Cow::Borrowed(""),
+        [value] => value.clone(),
+        _ => Cow::Owned(values.join(" ")),
+    }
+}
+
+impl<'a> FromIterator<Attribute<'a>> for Attributes<'a> {
+    fn from_iter<I: IntoIterator<Item = Attribute<'a>>>(iter: I) -> Self {
+        let mut attributes = Attributes::new();
+        iter.into_iter()
+            .for_each(|attribute| attributes.insert(attribute));
+        attributes
+    }
+}
+
+impl<'a, 'b> IntoIterator for &'b Attributes<'a> {
+    type Item = &'b Attribute<'a>;
+    type IntoIter = std::slice::Iter<'b, Attribute<'a>>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod attributes_tests {
+    use super::*;
+    #[test]
+    fn order_test() {
+        let mut attributes = Attributes::new();
+        for key in ["z", "a", "m", "b"] {
+            attributes.push_value(key, Cow::Borrowed("v"));
+        }
+        attributes.push_value("a", Cow::Borrowed("w"));
+        assert_eq!(
+            attributes.keys().collect::<Vec<_>>(),
+            vec!["z", "a", "m", "b"]
+        );
+        assert_eq!(
+            attributes.get("a").unwrap().values,
+            vec![Cow::Borrowed("v"), Cow::Borrowed("w")]
+        );
+        assert_eq!(attributes.get("a").unwrap().value, "v w");
+        assert_eq!(attributes.get("z").unwrap().value, "v");
+    }
+    #[test]
+    fn push_value_clears_raw_test() {
+        let mut attributes = Attributes::new();
+        attributes.insert(Attribute::new("class", vec![Cow::Borrowed("x")]).with_raw("x"));
+        attributes.push_value("class", Cow::Borrowed("y"));
+        assert_eq!(attributes.get("class").unwrap().raw, None);
+        assert_eq!(attributes.get("class").unwrap().value, "x y");
+    }
+    #[test]
+    fn insert_test() {
+        let mut attributes = Attributes::from_iter([
+            Attribute::new("id", vec![Cow::Borrowed("1")]).with_raw("1"),
+            Attribute::new("class", vec![Cow::Borrowed("a")]),
+        ]);
+        attributes.insert(Attribute::new("id", vec![Cow::Borrowed("2")]));
+        assert_eq!(attributes.keys().collect::<Vec<_>>(), vec!["id", "class"]);
+        assert_eq!(attributes.get("id").unwrap().raw, None);
+        assert_eq!(attributes.len(), 2);
+        assert!(!attributes.contains_key("none"));
+    }
+    #[test]
+    fn literal_test() {
+        let attribute = Attribute::literal("title", Cow::Borrowed("hello  world"));
+        assert_eq!(attribute.values, vec![Cow::Borrowed("hello  world")]);
+        assert_eq!(
+            Attribute::new("class", vec![Cow::Borrowed("a"), Cow::Borrowed("b")]).value,
+            "a b"
+        );
+    }
+    #[test]
+    fn remove_test() {
+        let mut attributes = Attributes::from_iter([
+            Attribute::new("class", vec![Cow::Borrowed("a"), Cow::Borrowed("b")]).with_raw("a  b"),
+            Attribute::new("id", vec![Cow::Borrowed("1")]),
+        ]);
+        assert!(attributes.remove_value("class", "a"));
+        assert!(!attributes.remove_value("class", "c"));
+        assert!(!attributes.remove_value("none", "a"));
+        let class = attributes.get("class").unwrap();
+        assert_eq!((class.value.as_ref(), class.raw), ("b", None));
+        assert!(attributes.remove_value("class", "b"));
+        assert_eq!(attributes.get("class").unwrap().value, "");
+        assert_eq!(
+            attributes.remove("id").map(|attribute| attribute.values),
+            Some(vec![Cow::Borrowed("1")])
+        );
+        assert_eq!(attributes.remove("id"), None);
+        assert_eq!(attributes.keys().collect::<Vec<_>>(), vec!["class"]);
+    }
+    #[test]
+    fn rename_test() {
+        let mut attributes = Attributes::from_iter([
+            Attribute::new("a", vec![Cow::Borrowed("1")]),
+            Attribute::new("b", vec![Cow::Borrowed("2")]),
+            Attribute::new("c", vec![Cow::Borrowed("3")]),
+        ]);
+        assert!(attributes.rename("b", "d"));
+        assert_eq!(attributes.keys().collect::<Vec<_>>(), vec!["a", "d", "c"]);
+        assert!(attributes.rename("d", "a"));
+        assert_eq!(attributes.keys().collect::<Vec<_>>(), vec!["a", "c"]);
+        assert_eq!(attributes.get("a").unwrap().value, "2");
+        assert!(attributes.rename("a", "a"));
+        assert!(!attributes.rename("none", "x"));
+        let mut attribute = Attribute::new("x:c", vec![Cow::Borrowed("3")]);
+        attribute.namespace_uri = Some(Cow::Borrowed("urn:x"));
+        attributes.insert(attribute);
+        assert!(attributes.rename("x:c", "x:d"));
+        assert_eq!(
+            attributes.get("x:d").unwrap().namespace_uri.as_deref(),
+            Some("urn:x")
+        );
+        assert!(attributes.rename("x:d", "d"));
+        assert_eq!(attributes.get("d").unwrap().namespace_uri, None);
+    }
+    #[test]
+    fn raw_is_ignored_by_eq_test() {
+        let attribute = Attribute::new("id", vec![Cow::Borrowed("1")]);
+        assert_eq!(attribute.clone().with_raw("1"), attribute);
+    }
+}
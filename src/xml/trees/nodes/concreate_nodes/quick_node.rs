@@ -1,23 +1,42 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
 
 use crate::xml::trees::nodes::{
+    attributes::{Attribute, Attributes},
     node_interface::{
         ElementInterface, NodeInterface, PropertyInterface, PropertyKey, PropertyValue,
     },
     node_type::NodeType,
 };
+use crate::xml::trees::span::Span;
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, Eq)]
 pub struct QuickNode<'a> {
     value: &'a str,
-    property: Option<HashMap<PropertyKey<'a>, PropertyValue<'a>>>,
+    property: Option<Attributes<'a>>,
     node_type: NodeType,
+    decoded: Cow<'a, str>,
+    data: Option<&'a str>,
+    namespace_uri: Option<Cow<'a, str>>,
+    span: Option<Span>,
+}
+
+// span only records where the node was written in the source,
+// so nodes built by hand compare equal to parsed ones
+impl PartialEq for QuickNode<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+            && self.decoded == other.decoded
+            && self.property == other.property
+            && self.node_type == other.node_type
+            && self.data == other.data
+            && self.namespace_uri == other.namespace_uri
+    }
 }
 
 impl<'a> QuickNode<'a> {
     pub fn new(value: &'a str, node_type: NodeType) -> Self {
         let property = if node_type == NodeType::Element {
-            Some(HashMap::new())
+            Some(Attributes::new())
         } else {
             None
         };
@@ -25,74 +44,145 @@ impl<'a> QuickNode<'a> {
             value,
             node_type,
             property,
+            decoded: Cow::Borrowed(value),
+            data: None,
+            namespace_uri: None,
+            span: None,
         }
     }
+    /// A text node written as `raw` in the source that reads as `decoded`.
+    pub fn text(raw: &'a str, decoded: Cow<'a, str>) -> Self {
+        let mut node = QuickNode::new(raw, NodeType::Text);
+        node.decoded = decoded;
+        node
+    }
     pub fn with_property(
         value: &'a str,
         node_type: NodeType,
-        property: Option<HashMap<PropertyKey<'a>, PropertyValue<'a>>>,
+        property: Option<Attributes<'a>>,
     ) -> Self {
         QuickNode {
             value,
             property,
             node_type,
+            decoded: Cow::Borrowed(value),
+            data: None,
+            namespace_uri: None,
+            span: None,
         }
     }
+    /// `<?target data?>`, the value of the node is the target.
+    pub fn processing_instruction(target: &'a str, data: &'a str) -> Self {
+        QuickNode {
+            value: target,
+            property: None,
+            node_type: NodeType::ProcessingInstruction,
+            decoded: Cow::Borrowed(target),
+            data: Some(data),
+            namespace_uri: None,
+            span: None,
+        }
+    }
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+    pub fn with_namespace_uri(mut self, namespace_uri: Cow<'a, str>) -> Self {
+        self.namespace_uri = Some(namespace_uri);
+        self
+    }
+    pub fn set_namespace_uri(&mut self, namespace_uri: Option<Cow<'a, str>>) {
+        self.namespace_uri = namespace_uri;
+    }
+    pub fn attributes(&self) -> Option<&Attributes<'a>> {
+        self.property.as_ref()
+    }
+    pub fn attributes_mut(&mut self) -> Option<&mut Attributes<'a>> {
+        self.property.as_mut()
+    }
 }
 
 impl<'a> ElementInterface<'a> for QuickNode<'a> {
-    fn change(&mut self, value: &'a str) -> () {
-        self.value = value
+    fn change(&mut self, value: &'a str) {
+        self.value = value;
+        self.decoded = Cow::Borrowed(value);
     }
     fn value(&self) -> &'a str {
         self.value
     }
+    fn decoded_value(&self) -> &str {
+        &self.decoded
+    }
 }
 impl<'a> PropertyInterface<'a> for QuickNode<'a> {
-    fn keys(&self) -> Option<Vec<PropertyKey>> {
-        self.property
-            .as_ref()
-            .map(|p| p.keys().map(|k| *k).collect())
+    fn keys(&self) -> Option<Vec<PropertyKey<'_>>> {
+        self.property.as_ref().map(|p| p.keys().collect())
     }
-    fn values(&self) -> Option<Vec<&PropertyValue>> {
-        self.property
-            .as_ref()
-            .map(|p| p.values().map(|v| v).collect())
+    fn values(&self) -> Option<Vec<&PropertyValue<'_>>> {
+        self.property.as_ref().map(|p| p.values().collect())
     }
     fn contains_key(&self, key: &str) -> bool {
-        if self.property.is_some() {
-            self.property.as_ref().unwrap().contains_key(key)
-        } else {
-            false
+        match &self.property {
+            Some(property) => property.contains_key(key),
+            None => false,
         }
     }
     fn contains_key_value(&self, key: &str, value: &str) -> bool {
-        if self.contains_key(key) {
-            self.property
-                .as_ref()
-                .unwrap()
-                .get(key)
-                .unwrap()
-                .contains(&value)
-        } else {
-            false
+        match self.property.as_ref().and_then(|p| p.get(key)) {
+            Some(attribute) => attribute.values.iter().any(|v| v == value),
+            None => false,
         }
     }
-    fn add_property(&mut self, key: &'a str, value: &'a str) -> () {
-        if self.property.is_some() {
-            if self.property.as_ref().unwrap().contains_key(key) {
-                self.property
-                    .as_mut()
-                    .unwrap()
-                    .get_mut(key)
-                    .as_mut()
-                    .unwrap()
-                    .push(value);
-                return;
-            }
-            self.property.as_mut().unwrap().insert(key, vec![value]);
+    fn add_property(&mut self, key: &'a str, value: &'a str) {
+        if let Some(property) = self.property.as_mut() {
+            property.push_value(key, Cow::Borrowed(value));
+        }
+    }
+    fn set_property(&mut self, key: &'a str, value: &'a str) {
+        if let Some(property) = self.property.as_mut() {
+            let mut attribute = Attribute::literal(key, Cow::Borrowed(value));
+            attribute.namespace_uri = property.get(key).and_then(|old| old.namespace_uri.clone());
+            property.insert(attribute);
         }
     }
+    fn remove_property(&mut self, key: &str) -> Option<PropertyValue<'a>> {
+        self.property
+            .as_mut()?
+            .remove(key)
+            .map(|attribute| attribute.values)
+    }
+    fn remove_property_value(&mut self, key: &str, value: &str) -> bool {
+        self.property
+            .as_mut()
+            .is_some_and(|property| property.remove_value(key, value))
+    }
+    fn rename_property(&mut self, from: &str, to: &'a str) -> bool {
+        self.property
+            .as_mut()
+            .is_some_and(|property| property.rename(from, to))
+    }
+    fn get(&self, key: &str) -> Option<&str> {
+        self.property
+            .as_ref()
+            .and_then(|p| p.get(key))
+            .map(|attribute| attribute.value.as_ref())
+    }
+    fn get_ns(&self, namespace_uri: &str, local_name: &str) -> Option<&str> {
+        self.property
+            .as_ref()?
+            .iter()
+            .find(|attribute| {
+                attribute.namespace_uri.as_deref() == Some(namespace_uri)
+                    && attribute.local_name() == local_name
+            })
+            .map(|attribute| attribute.value.as_ref())
+    }
+    fn raw_property(&self, key: &str) -> Option<&'a str> {
+        self.property
+            .as_ref()
+            .and_then(|p| p.get(key))
+            .and_then(|attribute| attribute.raw)
+    }
 }
 
 impl<'a> NodeInterface<'a> for QuickNode<'a> {
@@ -102,69 +192,127 @@ impl<'a> NodeInterface<'a> for QuickNode<'a> {
     fn is_text_type(&self) -> bool {
         self.node_type == NodeType::Text
     }
+    fn is_comment_type(&self) -> bool {
+        self.node_type == NodeType::Comment
+    }
+    fn is_cdata_type(&self) -> bool {
+        self.node_type == NodeType::CData
+    }
+    fn is_processing_instruction_type(&self) -> bool {
+        self.node_type == NodeType::ProcessingInstruction
+    }
+    fn data(&self) -> Option<&str> {
+        self.data
+    }
+    fn namespace_uri(&self) -> Option<&str> {
+        self.namespace_uri.as_deref()
+    }
+    fn span(&self) -> Option<Span> {
+        self.span
+    }
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod quick_node_test {
-    use crate::xml::trees::nodes::{
-        node_interface::{ElementInterface, NodeInterface, PropertyInterface},
-        node_type::NodeType,
+    use crate::xml::trees::{
+        nodes::{
+            attributes::{Attribute, Attributes},
+            node_interface::{ElementInterface, NodeInterface, PropertyInterface},
+            node_type::NodeType,
+        },
+        span::{Position, Span},
     };
-    use std::collections::HashMap;
+    use std::borrow::Cow;
 
     use super::QuickNode;
     #[test]
     fn values_test() {
-        let mut hash = HashMap::new();
-        hash.insert("key", vec!["value"]);
-        hash.insert("key2", vec!["value2"]);
-        hash.insert("key3", vec!["value3"]);
+        let mut attributes = Attributes::new();
+        attributes.insert(Attribute::new("key", vec![Cow::Borrowed("value")]));
+        attributes.insert(Attribute::new("key2", vec![Cow::Borrowed("value2")]));
+        attributes.insert(Attribute::new("key3", vec![Cow::Borrowed("value3")]));
         let node = QuickNode {
             value: "test",
-            property: Some(hash),
+            property: Some(attributes),
             node_type: NodeType::Element,
+            decoded: Cow::Borrowed("test"),
+            data: None,
+            namespace_uri: None,
+            span: None,
         };
         let values = node.values();
-        assert_eq!(values.clone().unwrap().contains(&&vec!["value"]), true);
-        assert_eq!(values.clone().unwrap().contains(&&vec!["value2"]), true);
-        assert_eq!(values.clone().unwrap().contains(&&vec!["value3"]), true);
+        assert_eq!(
+            values
+                .clone()
+                .unwrap()
+                .contains(&&vec![Cow::Borrowed("value")]),
+            true
+        );
+        assert_eq!(
+            values
+                .clone()
+                .unwrap()
+                .contains(&&vec![Cow::Borrowed("value2")]),
+            true
+        );
+        assert_eq!(
+            values
+                .clone()
+                .unwrap()
+                .contains(&&vec![Cow::Borrowed("value3")]),
+            true
+        );
     }
     #[test]
     fn keys_test() {
-        let mut hash = HashMap::new();
-        hash.insert("key", vec!["value"]);
-        hash.insert("key2", vec!["value"]);
-        hash.insert("key3", vec!["value"]);
+        let mut attributes = Attributes::new();
+        attributes.insert(Attribute::new("key", vec![Cow::Borrowed("value")]));
+        attributes.insert(Attribute::new("key2", vec![Cow::Borrowed("value")]));
+        attributes.insert(Attribute::new("key3", vec![Cow::Borrowed("value")]));
         let node = QuickNode {
             value: "test",
-            property: Some(hash),
+            property: Some(attributes),
             node_type: NodeType::Element,
+            decoded: Cow::Borrowed("test"),
+            data: None,
+            namespace_uri: None,
+            span: None,
         };
         let keys = node.keys();
         assert_eq!(keys.clone().unwrap().contains(&"key"), true);
         assert_eq!(keys.clone().unwrap().contains(&"key2"), true);
         assert_eq!(keys.clone().unwrap().contains(&"key3"), true);
+        assert_eq!(keys.unwrap(), vec!["key", "key2", "key3"]);
     }
     #[test]
     fn containes_key_value_test() {
-        let mut hash = HashMap::new();
-        hash.insert("key", vec!["value"]);
+        let mut attributes = Attributes::new();
+        attributes.insert(Attribute::new("key", vec![Cow::Borrowed("value")]));
         let node = QuickNode {
             value: "test",
-            property: Some(hash),
+            property: Some(attributes),
             node_type: NodeType::Element,
+            decoded: Cow::Borrowed("test"),
+            data: None,
+            namespace_uri: None,
+            span: None,
         };
         assert_eq!(node.contains_key_value("key", "value"), true);
         assert_eq!(node.contains_key_value("key", "value2"), false);
     }
     #[test]
     fn containes_key_test() {
-        let mut hash = HashMap::new();
-        hash.insert("key", vec!["value"]);
+        let mut attributes = Attributes::new();
+        attributes.insert(Attribute::new("key", vec![Cow::Borrowed("value")]));
         let node = QuickNode {
             value: "test",
-            property: Some(hash),
+            property: Some(attributes),
             node_type: NodeType::Element,
+            decoded: Cow::Borrowed("test"),
+            data: None,
+            namespace_uri: None,
+            span: None,
         };
         assert_eq!(node.contains_key("key"), true);
         assert_eq!(node.contains_key("key2"), false);
@@ -172,21 +320,49 @@ mod quick_node_test {
             value: "test",
             property: None,
             node_type: NodeType::Text,
+            decoded: Cow::Borrowed("test"),
+            data: None,
+            namespace_uri: None,
+            span: None,
         };
         assert_eq!(node.contains_key("key"), false);
     }
     #[test]
+    fn change_property_test() {
+        let mut node = QuickNode::new("test", NodeType::Element);
+        node.add_property("class", "a");
+        node.add_property("class", "b");
+        node.add_property("id", "1");
+        node.set_property("id", "2");
+        assert_eq!(node.get("id"), Some("2"));
+        assert!(node.remove_property_value("class", "a"));
+        assert_eq!(node.get("class"), Some("b"));
+        assert!(!node.contains_key_value("class", "a"));
+        assert!(node.rename_property("id", "key"));
+        assert_eq!(node.keys(), Some(vec!["class", "key"]));
+        assert_eq!(node.remove_property("key"), Some(vec![Cow::Borrowed("2")]));
+        assert_eq!(node.remove_property("key"), None);
+        let mut text = QuickNode::new("text", NodeType::Text);
+        text.set_property("id", "1");
+        assert!(!text.contains_key("id"));
+        assert!(!text.rename_property("id", "key"));
+    }
+    #[test]
     fn add_property_test() {
         let mut node = QuickNode::new("test", NodeType::Element);
         node.add_property("key", "value");
-        let mut hash = HashMap::new();
-        hash.insert("key", vec!["value"]);
+        let mut attributes = Attributes::new();
+        attributes.insert(Attribute::new("key", vec![Cow::Borrowed("value")]));
         assert_eq!(
             node,
             QuickNode {
                 value: "test",
-                property: Some(hash),
-                node_type: NodeType::Element
+                property: Some(attributes),
+                node_type: NodeType::Element,
+                decoded: Cow::Borrowed("test"),
+                data: None,
+                namespace_uri: None,
+                span: None,
             }
         );
     }
@@ -196,6 +372,49 @@ mod quick_node_test {
         node.change("data");
         assert_eq!(node.value(), "data");
     }
+    #[test]
+    fn is_comment_type_test() {
+        let node = QuickNode::new("test", NodeType::Comment);
+        assert!(node.is_comment_type());
+        assert!(!node.is_text_type());
+        let node = QuickNode::new("test", NodeType::Text);
+        assert!(!node.is_comment_type());
+    }
+    #[test]
+    fn is_character_data_test() {
+        assert!(QuickNode::new("test", NodeType::Text).is_character_data());
+        assert!(QuickNode::new("test", NodeType::CData).is_character_data());
+        assert!(QuickNode::new("test", NodeType::CData).is_cdata_type());
+        assert!(!QuickNode::new("test", NodeType::Comment).is_character_data());
+    }
+    #[test]
+    fn processing_instruction_test() {
+        let node = QuickNode::processing_instruction("xml-stylesheet", r#"href="a.xsl""#);
+        assert!(node.is_processing_instruction_type());
+        assert_eq!(node.value(), "xml-stylesheet");
+        assert_eq!(node.data(), Some(r#"href="a.xsl""#));
+        assert!(!QuickNode::new("test", NodeType::Element).is_processing_instruction_type());
+    }
+    #[test]
+    fn span_test() {
+        let node = QuickNode::new("test", NodeType::Element);
+        assert_eq!(node.span(), None);
+        let span = Span::new(
+            Position {
+                offset: 0,
+                line: 1,
+                column: 1,
+            },
+            Position {
+                offset: 6,
+                line: 1,
+                column: 7,
+            },
+        );
+        let node = node.with_span(span);
+        assert_eq!(node.span(), Some(span));
+        assert_eq!(node, QuickNode::new("test", NodeType::Element));
+    }
 
     #[test]
     fn is_element_type_test() {
@@ -1,19 +1,83 @@
+use std::borrow::Cow;
+
+use crate::xml::trees::span::Span;
+
 pub trait NodeInterface<'a>: ElementInterface<'a> + PropertyInterface<'a> {
     fn is_element_type(&self) -> bool;
     fn is_text_type(&self) -> bool;
+    fn is_comment_type(&self) -> bool;
+    fn is_cdata_type(&self) -> bool;
+    fn is_processing_instruction_type(&self) -> bool;
+    /// Text and CDATA sections both carry character data.
+    fn is_character_data(&self) -> bool {
+        self.is_text_type() || self.is_cdata_type()
+    }
+    /// The data of a processing instruction.
+    fn data(&self) -> Option<&str> {
+        None
+    }
+    /// The part of an element name before `:`.
+    fn prefix(&self) -> Option<&'a str> {
+        if !self.is_element_type() {
+            return None;
+        }
+        self.value().split_once(':').map(|(prefix, _)| prefix)
+    }
+    /// The element name without its prefix.
+    fn local_name(&self) -> &'a str {
+        match self.prefix() {
+            Some(prefix) => &self.value()[prefix.len() + 1..],
+            None => self.value(),
+        }
+    }
+    /// The namespace the prefix of an element resolves to.
+    fn namespace_uri(&self) -> Option<&str> {
+        None
+    }
+    /// Where the node was read from, `None` for nodes built by hand.
+    fn span(&self) -> Option<Span> {
+        None
+    }
 }
 pub type PropertyKey<'a> = &'a str;
-pub type PropertyValue<'a> = Vec<&'a str>;
+pub type PropertyValue<'a> = Vec<Cow<'a, str>>;
 pub trait ElementInterface<'a> {
-    fn change(&mut self, value: &'a str) -> ();
+    fn change(&mut self, value: &'a str);
+    /// The value as written in the source, references are not decoded.
     fn value(&self) -> &'a str;
+    /// The value with entity and character references decoded.
+    fn decoded_value(&self) -> &str;
 }
 pub trait PropertyInterface<'a> {
-    fn keys(&self) -> Option<Vec<PropertyKey>>;
-    fn values(&self) -> Option<Vec<&PropertyValue>>;
+    fn keys(&self) -> Option<Vec<PropertyKey<'_>>>;
+    fn values(&self) -> Option<Vec<&PropertyValue<'_>>>;
     fn contains_key(&self, key: &str) -> bool;
     fn contains_key_value(&self, key: &str, value: &str) -> bool;
-    fn add_property(&mut self, key: &'a str, value: &'a str) -> ();
-    //fn change_property(&mut self, key: &'a str, new_value: &'a str) -> ();
-    //fn remove_property(&mut self, key: &'a str) -> Option<PropertyValue>;
+    fn add_property(&mut self, key: &'a str, value: &'a str);
+    /// The whole value of `key` with references decoded, spacing is kept.
+    fn get(&self, key: &str) -> Option<&str>;
+    /// The value of `key` split on whitespace, like the classes of `class="a b"`.
+    fn tokens(&self, key: &str) -> Option<Vec<&str>> {
+        self.get(key)
+            .map(|value| value.split_ascii_whitespace().collect())
+    }
+    /// The whole value of the attribute named `local_name` in `namespace_uri`.
+    fn get_ns(&self, _namespace_uri: &str, _local_name: &str) -> Option<&str> {
+        None
+    }
+    /// The value of `key` as written between the quotes in the source.
+    fn raw_property(&self, _key: &str) -> Option<&'a str> {
+        None
+    }
+    /// Replace the values of `key` with `value` kept whole, adding the key
+    /// when it is new.
+    fn set_property(&mut self, key: &'a str, value: &'a str);
+    /// Remove `key`, returning its values.
+    fn remove_property(&mut self, key: &str) -> Option<PropertyValue<'a>>;
+    /// Remove one value of `key`, like one class of `class="a b"`.
+    /// Returns whether the value was there.
+    fn remove_property_value(&mut self, key: &str, value: &str) -> bool;
+    /// Rename `from` to `to`, replacing the values of `to` if it is there.
+    /// Returns whether `from` was there.
+    fn rename_property(&mut self, from: &str, to: &'a str) -> bool;
 }
@@ -3,5 +3,7 @@ pub enum NodeType {
     Element,
     Text,
     Comment,
+    CData,
+    ProcessingInstruction,
     Script,
 }
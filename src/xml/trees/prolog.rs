@@ -0,0 +1,87 @@
+use crate::xml::trees::{nodes::concreate_nodes::quick_node::QuickNode, tree::XmlTree};
+
+/// The `<?xml version="1.0" encoding="UTF-8" standalone="yes"?>` line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct XmlDeclaration<'a> {
+    pub version: &'a str,
+    pub encoding: Option<&'a str>,
+    pub standalone: Option<bool>,
+}
+
+impl<'a> XmlDeclaration<'a> {
+    pub fn new(version: &'a str) -> Self {
+        XmlDeclaration {
+            version,
+            encoding: None,
+            standalone: None,
+        }
+    }
+    pub fn to_xml_string(&self) -> String {
+        let mut xml = format!(r#"<?xml version="{}""#, self.version);
+        if let Some(encoding) = self.encoding {
+            xml.push_str(&format!(r#" encoding="{}""#, encoding));
+        }
+        if let Some(standalone) = self.standalone {
+            let standalone = if standalone { "yes" } else { "no" };
+            xml.push_str(&format!(r#" standalone="{}""#, standalone));
+        }
+        xml.push_str("?>");
+        xml
+    }
+}
+
+/// The `<!DOCTYPE name PUBLIC "public id" "system id" [internal subset]>` declaration.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Doctype<'a> {
+    pub name: &'a str,
+    pub public_id: Option<&'a str>,
+    pub system_id: Option<&'a str>,
+    /// The declarations between `[` and `]`, as written.
+    pub internal_subset: Option<&'a str>,
+}
+
+impl<'a> Doctype<'a> {
+    pub fn new(name: &'a str) -> Self {
+        Doctype {
+            name,
+            public_id: None,
+            system_id: None,
+            internal_subset: None,
+        }
+    }
+    pub fn to_xml_string(&self) -> String {
+        let literal = |id: &str| {
+            let quote = if id.contains('"') { '\'' } else { '"' };
+            format!(" {}{}{}", quote, id, quote)
+        };
+        let mut xml = format!("<!DOCTYPE {}", self.name);
+        match (self.public_id, self.system_id) {
+            (Some(public_id), Some(system_id)) => {
+                xml.push_str(" PUBLIC");
+                xml.push_str(&literal(public_id));
+                xml.push_str(&literal(system_id));
+            }
+            (None, Some(system_id)) => {
+                xml.push_str(" SYSTEM");
+                xml.push_str(&literal(system_id));
+            }
+            _ => (),
+        }
+        if let Some(internal_subset) = self.internal_subset {
+            xml.push_str(&format!(" [{}]", internal_subset));
+        }
+        xml.push('>');
+        xml
+    }
+}
+
+/// Everything in front of the root element.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Prolog<'a> {
+    pub declaration: Option<XmlDeclaration<'a>>,
+    pub doctype: Option<Doctype<'a>>,
+    /// How many of `nodes` come before the doctype.
+    pub doctype_index: usize,
+    /// Comments and processing instructions before the root, in source order.
+    pub nodes: Vec<XmlTree<'a, QuickNode<'a>>>,
+}
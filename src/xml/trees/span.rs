@@ -0,0 +1,186 @@
+use std::{cell::Cell, ops::Range};
+
+/// A location in the source. `line` and `column` start from 1 and the
+/// column is counted in characters, `offset` is the byte offset.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Position {
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+}
+
+impl Position {
+    /// The position right after `text`, which must start at this position.
+    pub fn advanced(&self, text: &str) -> Position {
+        match text.rfind('\n') {
+            Some(last_newline) => Position {
+                offset: self.offset + text.len(),
+                line: self.line + text.matches('\n').count(),
+                column: text[last_newline + 1..].chars().count() + 1,
+            },
+            None => Position {
+                offset: self.offset + text.len(),
+                line: self.line,
+                column: self.column + text.chars().count(),
+            },
+        }
+    }
+}
+
+/// The source range a token or node was read from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    pub fn new(start: Position, end: Position) -> Self {
+        Span { start, end }
+    }
+    pub fn range(&self) -> Range<usize> {
+        self.start.offset..self.end.offset
+    }
+}
+
+/// Byte offsets of every line start, used to turn offsets into positions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct LineIndex<'a> {
+    source: &'a str,
+    line_starts: Vec<usize>,
+    /// The last position looked up. Offsets are mostly asked for in
+    /// increasing order, so columns are counted on from there instead of
+    /// from the line start, which keeps long lines linear.
+    last: Cell<Position>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(source: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(
+                source
+                    .bytes()
+                    .enumerate()
+                    .filter(|(_, c)| *c == b'\n')
+                    .map(|(i, _)| i + 1),
+            )
+            .collect();
+        LineIndex {
+            source,
+            line_starts,
+            last: Cell::new(Position::default()),
+        }
+    }
+    pub fn position(&self, offset: usize) -> Position {
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next_line) => next_line - 1,
+        };
+        let last = self.last.get();
+        let (from, column) = if last.line == line + 1 && last.offset <= offset {
+            (last.offset, last.column - 1)
+        } else {
+            (self.line_starts[line], 0)
+        };
+        let column = column
+            + self
+                .source
+                .get(from..offset)
+                .map(|s| s.chars().count())
+                .unwrap_or(offset - from);
+        let position = Position {
+            offset,
+            line: line + 1,
+            column: column + 1,
+        };
+        if self.source.is_char_boundary(offset) {
+            self.last.set(position);
+        }
+        position
+    }
+    pub fn span(&self, range: Range<usize>) -> Span {
+        Span::new(self.position(range.start), self.position(range.end))
+    }
+}
+
+#[cfg(test)]
+mod span_tests {
+    use super::*;
+    #[test]
+    fn position_test() {
+        let index = LineIndex::new("<a>\n  <b/>\n</a>");
+        assert_eq!(
+            index.position(0),
+            Position {
+                offset: 0,
+                line: 1,
+                column: 1
+            }
+        );
+        assert_eq!(
+            index.position(6),
+            Position {
+                offset: 6,
+                line: 2,
+                column: 3
+            }
+        );
+        assert_eq!(
+            index.position(11),
+            Position {
+                offset: 11,
+                line: 3,
+                column: 1
+            }
+        );
+        let index = LineIndex::new("<あ>x</あ>");
+        assert_eq!(index.position(5).column, 4);
+    }
+    #[test]
+    fn position_order_test() {
+        let source = "<é>\n<a>ü</a><b/>\n";
+        let index = LineIndex::new(source);
+        let expected: Vec<_> = (0..=source.len())
+            .filter(|i| source.is_char_boundary(*i))
+            .map(|i| LineIndex::new(source).position(i))
+            .collect();
+        for position in expected.iter().chain(expected.iter().rev()) {
+            assert_eq!(index.position(position.offset), *position);
+        }
+    }
+    #[test]
+    fn long_line_test() {
+        let source = "<a/>".repeat(200_000);
+        let index = LineIndex::new(&source);
+        let start = std::time::Instant::now();
+        for offset in (0..source.len()).step_by(4) {
+            index.position(offset);
+        }
+        assert_eq!(index.position(source.len()).column, source.len() + 1);
+        // counting from the line start every time takes minutes here
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+    }
+    #[test]
+    fn advanced_test() {
+        let source = "<a>\n  <b x=\"&bad;\"/>\n</a>";
+        let index = LineIndex::new(source);
+        let start = index.position(4);
+        assert_eq!(start.advanced("  <b x=\""), index.position(12));
+        assert_eq!(index.position(0).advanced("<a>\n  <b"), index.position(8));
+    }
+    #[test]
+    fn span_range_test() {
+        let index = LineIndex::new("<div>data</div>");
+        assert_eq!(index.span(5..9).range(), 5..9);
+    }
+}
@@ -1,8 +1,14 @@
-use crate::xml::trees::nodes::node_interface::NodeInterface;
+pub mod iter;
+
+use crate::xml::trees::{
+    nodes::node_interface::{NodeInterface, PropertyValue},
+    span::Span,
+};
+use iter::{BreadthFirst, Descendants, Elements, Texts, Traverse};
 use std::marker::PhantomData;
 
+#[allow(clippy::box_collection)]
 #[derive(Clone, Debug, PartialEq, Eq)]
-
 pub struct XmlTree<'a, T: NodeInterface<'a>> {
     root: T,
     children: Option<Box<Vec<XmlTree<'a, T>>>>,
@@ -10,6 +16,7 @@ pub struct XmlTree<'a, T: NodeInterface<'a>> {
 }
 
 impl<'a, T: NodeInterface<'a>> XmlTree<'a, T> {
+    #[allow(clippy::box_collection)]
     pub fn new(root: T, children: Option<Box<Vec<XmlTree<'a, T>>>>) -> Self {
         XmlTree {
             root,
@@ -19,51 +26,233 @@ impl<'a, T: NodeInterface<'a>> XmlTree<'a, T> {
     }
 }
 impl<'a, T: NodeInterface<'a>> XmlTree<'a, T> {
-    //pub fn change_property(&mut self, key: &'a str, new_value: &'a str) {
-    //self.root.change_property(key, new_value)
-    //}
     pub fn append_children(&mut self, child: XmlTree<'a, T>) {
-        if self.children.is_some() {
-            self.children.as_mut().unwrap().push(child)
-        } else {
-            self.children = Some(Box::new(vec![child]))
+        match self.children.as_mut() {
+            Some(children) => children.push(child),
+            None => self.children = Some(Box::new(vec![child])),
         }
     }
-    pub fn get_elements_by_key_value(&self, key: &str, value: &str) -> Vec<&XmlTree<'a, T>> {
-        let mut result = vec![];
-        if self.root.contains_key_value(key, value) {
-            result.push(self);
+    pub fn prepend_child(&mut self, child: XmlTree<'a, T>) {
+        self.insert_child(0, child)
+    }
+    /// Insert `child` before the child at `index`.
+    /// Panics when `index` is greater than the number of children.
+    pub fn insert_child(&mut self, index: usize, child: XmlTree<'a, T>) {
+        assert!(index <= self.len(), "child index {} out of bounds", index);
+        self.children
+            .get_or_insert_with(Default::default)
+            .insert(index, child);
+    }
+    /// Panics when there is no child at `index`.
+    pub fn remove_child(&mut self, index: usize) -> XmlTree<'a, T> {
+        assert!(index < self.len(), "child index {} out of bounds", index);
+        let children = self.children.as_mut().unwrap();
+        let child = children.remove(index);
+        if children.is_empty() {
+            self.children = None;
+        }
+        child
+    }
+    /// Put `child` in place of the child at `index`, returning the old one.
+    /// Panics when there is no child at `index`.
+    pub fn replace_child(&mut self, index: usize, child: XmlTree<'a, T>) -> XmlTree<'a, T> {
+        assert!(index < self.len(), "child index {} out of bounds", index);
+        std::mem::replace(&mut self.children.as_mut().unwrap()[index], child)
+    }
+    /// Keep only the children for which `keep` returns true.
+    pub fn retain_children(&mut self, keep: impl FnMut(&XmlTree<'a, T>) -> bool) {
+        if let Some(children) = self.children.as_mut() {
+            children.retain(keep);
+            if children.is_empty() {
+                self.children = None;
+            }
         }
-        if self.children.is_some() {
-            self.children.as_ref().unwrap().iter().for_each(|child| {
-                result.extend(child.get_elements_by_key_value(key, value));
-            });
+    }
+    /// Remove the first tree below this one, in document order, for which
+    /// `is_target` returns true, and hand it over with its children.
+    /// The trees below a target are not searched.
+    pub fn detach(
+        &mut self,
+        mut is_target: impl FnMut(&XmlTree<'a, T>) -> bool,
+    ) -> Option<XmlTree<'a, T>> {
+        self.detach_first(&mut is_target)
+    }
+    fn detach_first(
+        &mut self,
+        is_target: &mut impl FnMut(&XmlTree<'a, T>) -> bool,
+    ) -> Option<XmlTree<'a, T>> {
+        for index in 0..self.len() {
+            if is_target(&self.children()[index]) {
+                return Some(self.remove_child(index));
+            }
+            let child = &mut self.children.as_mut().unwrap()[index];
+            if let Some(detached) = child.detach_first(is_target) {
+                return Some(detached);
+            }
         }
-        result
+        None
     }
-    pub fn get_elements_by_node_value(&self, value: &str) -> Vec<&XmlTree<'a, T>> {
-        let mut result = vec![];
-        if self.root.value() == value {
-            result.push(self);
+    /// Detach every child from this tree and hand them over, like to move
+    /// them under another parent. Use `remove_child` to detach one child.
+    pub fn take_children(&mut self) -> Vec<XmlTree<'a, T>> {
+        self.children
+            .take()
+            .map_or_else(Vec::new, |children| *children)
+    }
+    pub fn set_property(&mut self, key: &'a str, value: &'a str) {
+        self.root.set_property(key, value)
+    }
+    pub fn remove_property(&mut self, key: &str) -> Option<PropertyValue<'a>> {
+        self.root.remove_property(key)
+    }
+    pub fn remove_property_value(&mut self, key: &str, value: &str) -> bool {
+        self.root.remove_property_value(key, value)
+    }
+    pub fn rename_property(&mut self, from: &str, to: &'a str) -> bool {
+        self.root.rename_property(from, to)
+    }
+    /// Call `f` on this tree and every tree below it in document order,
+    /// to change the elements a search would find. The children are
+    /// visited after `f` has run on their parent.
+    pub fn for_each_mut(&mut self, mut f: impl FnMut(&mut XmlTree<'a, T>)) {
+        self.visit_mut(&mut f)
+    }
+    fn visit_mut(&mut self, f: &mut impl FnMut(&mut XmlTree<'a, T>)) {
+        f(self);
+        if let Some(children) = self.children.as_mut() {
+            children.iter_mut().for_each(|child| child.visit_mut(f));
         }
-        if self.children.is_some() {
-            self.children.as_ref().unwrap().iter().for_each(|child| {
-                result.extend(child.get_elements_by_node_value(value));
-            });
+    }
+    /// The first of this tree and the trees below it, in document order,
+    /// for which `is_target` returns true, to change in place.
+    pub fn find_mut(
+        &mut self,
+        mut is_target: impl FnMut(&XmlTree<'a, T>) -> bool,
+    ) -> Option<&mut XmlTree<'a, T>> {
+        self.find_first_mut(&mut is_target)
+    }
+    fn find_first_mut(
+        &mut self,
+        is_target: &mut impl FnMut(&XmlTree<'a, T>) -> bool,
+    ) -> Option<&mut XmlTree<'a, T>> {
+        if is_target(self) {
+            return Some(self);
         }
-        result
+        self.children
+            .as_mut()?
+            .iter_mut()
+            .find_map(|child| child.find_first_mut(is_target))
+    }
+    /// The first element named `name`, like `get_elements_by_node_value`.
+    pub fn find_by_name_mut(&mut self, name: &str) -> Option<&mut XmlTree<'a, T>> {
+        self.find_mut(|tree| tree.root.is_element_type() && tree.root.value() == name)
+    }
+    /// Where the root node was parsed from. For elements this is the start tag.
+    pub fn span(&self) -> Option<Span> {
+        self.root.span()
+    }
+    pub fn node(&self) -> &T {
+        &self.root
+    }
+    pub fn node_mut(&mut self) -> &mut T {
+        &mut self.root
+    }
+    /// The child trees in document order, empty when there are none.
+    pub fn children(&self) -> &[XmlTree<'a, T>] {
+        self.children.as_deref().map_or(&[], Vec::as_slice)
+    }
+    /// The child trees in document order, to change in place.
+    pub fn children_mut(&mut self) -> &mut [XmlTree<'a, T>] {
+        self.children
+            .as_deref_mut()
+            .map_or(&mut [], Vec::as_mut_slice)
+    }
+    pub fn child(&self, index: usize) -> Option<&XmlTree<'a, T>> {
+        self.children().get(index)
+    }
+    pub fn child_mut(&mut self, index: usize) -> Option<&mut XmlTree<'a, T>> {
+        self.children_mut().get_mut(index)
+    }
+    pub fn first_child(&self) -> Option<&XmlTree<'a, T>> {
+        self.children().first()
+    }
+    pub fn first_child_mut(&mut self) -> Option<&mut XmlTree<'a, T>> {
+        self.children_mut().first_mut()
+    }
+    pub fn last_child(&self) -> Option<&XmlTree<'a, T>> {
+        self.children().last()
+    }
+    pub fn last_child_mut(&mut self) -> Option<&mut XmlTree<'a, T>> {
+        self.children_mut().last_mut()
+    }
+    /// Take the tree apart into its root node and its children.
+    pub fn into_parts(self) -> (T, Vec<XmlTree<'a, T>>) {
+        let children = self.children.map_or_else(Vec::new, |children| *children);
+        (self.root, children)
+    }
+    /// The number of children.
+    pub fn len(&self) -> usize {
+        self.children().len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.children().is_empty()
+    }
+    /// This tree and every tree below it in document order.
+    pub fn descendants(&self) -> Descendants<'_, 'a, T> {
+        Descendants::new(self)
+    }
+    /// The start and end edges of this tree and every tree below it,
+    /// like walking over the tags of the source.
+    pub fn traverse(&self) -> Traverse<'_, 'a, T> {
+        Traverse::new(self)
+    }
+    /// This tree and every tree below it, one level at a time.
+    pub fn breadth_first(&self) -> BreadthFirst<'_, 'a, T> {
+        BreadthFirst::new(self)
+    }
+    /// The element trees among the descendants.
+    pub fn elements(&self) -> Elements<'_, 'a, T> {
+        Elements::new(self)
+    }
+    /// The decoded text of every text and CDATA node below, in document order.
+    pub fn texts(&self) -> Texts<'_, 'a, T> {
+        Texts::new(self)
+    }
+    pub fn get_elements_by_key_value(&self, key: &str, value: &str) -> Vec<&XmlTree<'a, T>> {
+        self.descendants()
+            .filter(|tree| tree.root.contains_key_value(key, value))
+            .collect()
+    }
+    pub fn get_elements_by_node_value(&self, value: &str) -> Vec<&XmlTree<'a, T>> {
+        self.descendants()
+            .filter(|tree| tree.root.value() == value)
+            .collect()
+    }
+    /// The elements named `local_name` in the namespace `namespace_uri`,
+    /// whatever prefix they were written with.
+    pub fn get_elements_by_ns_name(
+        &self,
+        namespace_uri: &str,
+        local_name: &str,
+    ) -> Vec<&XmlTree<'a, T>> {
+        self.elements()
+            .filter(|tree| {
+                tree.root.namespace_uri() == Some(namespace_uri)
+                    && tree.root.local_name() == local_name
+            })
+            .collect()
     }
     pub fn text_contents(&self) -> Option<Vec<&str>> {
         self.children.as_ref().map(|child| {
             child
                 .iter()
-                .filter(|child| child.root.is_text_type())
-                .map(|child| child.root.value())
+                .filter(|child| child.root.is_character_data())
+                .map(|child| child.root.decoded_value())
                 .collect::<Vec<_>>()
         })
     }
     ///
-    /// Return child all text
+    /// Return child all text, the text runs joined as they are
     /// ## Example
     /// ```rust
     ///     let source = r#"<div>
@@ -78,8 +267,8 @@ impl<'a, T: NodeInterface<'a>> XmlTree<'a, T> {
     /// ```
     ///
     pub fn concat_all_text(&self) -> String {
-        if self.root.is_text_type() {
-            return format!("{}", self.root.value());
+        if self.root.is_character_data() {
+            return self.root.decoded_value().to_string();
         }
         if self.children.is_none() {
             return "".to_string();
@@ -88,42 +277,144 @@ impl<'a, T: NodeInterface<'a>> XmlTree<'a, T> {
             .as_ref()
             .unwrap()
             .iter()
-            .fold("".to_string(), |acc, cur| {
-                if acc.len() == 0 {
-                    format!("{}", cur.concat_all_text())
-                } else {
-                    format!("{} {}", acc, cur.concat_all_text())
+            .filter(|child| child.root.is_element_type() || child.root.is_character_data())
+            .map(|child| child.concat_all_text())
+            .collect()
+    }
+    /// Write the tree back as xml. Values are written as they were read, so
+    /// text built by hand must already have its `<` and `&` escaped.
+    /// Attributes added by hand are escaped.
+    pub fn to_xml_string(&self) -> String {
+        let mut xml = String::new();
+        self.write_xml(&mut xml);
+        xml
+    }
+    fn write_xml(&self, xml: &mut String) {
+        let node = &self.root;
+        if node.is_text_type() {
+            xml.push_str(node.value());
+        } else if node.is_cdata_type() {
+            xml.push_str(&format!("<![CDATA[{}]]>", node.value()));
+        } else if node.is_comment_type() {
+            xml.push_str(&format!("<!--{}-->", node.value()));
+        } else if node.is_processing_instruction_type() {
+            match node.data() {
+                Some(data) if !data.is_empty() => {
+                    xml.push_str(&format!("<?{} {}?>", node.value(), data))
                 }
-            })
+                _ => xml.push_str(&format!("<?{}?>", node.value())),
+            }
+        } else if node.is_element_type() {
+            xml.push('<');
+            xml.push_str(node.value());
+            for key in node.keys().unwrap_or_default() {
+                let value = match node.raw_property(key) {
+                    Some(raw) => raw.to_string(),
+                    None => escape_attribute(node.get(key).unwrap_or_default()),
+                };
+                let quote = if value.contains('"') { '\'' } else { '"' };
+                xml.push_str(&format!(" {}={}{}{}", key, quote, value, quote));
+            }
+            match self.children.as_ref() {
+                Some(children) if !children.is_empty() => {
+                    xml.push('>');
+                    children.iter().for_each(|child| child.write_xml(xml));
+                    xml.push_str(&format!("</{}>", node.value()));
+                }
+                _ => xml.push_str("/>"),
+            }
+        }
     }
 }
-#[cfg(test)]
 
+fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('"', "&quot;")
+}
+#[cfg(test)]
 mod xml_tree_tests {
-    use crate::xml::trees::nodes::{node_interface::PropertyInterface, node_type::NodeType};
+    use crate::xml::trees::nodes::{
+        node_interface::{ElementInterface, PropertyInterface},
+        node_type::NodeType,
+    };
 
     use super::{mock_node::MockNode, XmlTree};
-    //#[test]
-    //fn change_property_test() {
-    //let mut root = MockNode::new("root");
-    //root.add_property("id", "kai");
+    #[test]
+    fn change_property_test() {
+        let mut root = MockNode::new("root");
+        root.add_property("id", "kai");
+        root.add_property("class", "a");
+        root.add_property("class", "b");
+        let mut root = XmlTree::new(root, None);
+        root.set_property("id", "iak");
+        assert!(root.remove_property_value("class", "a"));
+        assert!(!root.remove_property_value("class", "c"));
+        assert!(root.rename_property("class", "kind"));
+        assert!(!root.rename_property("class", "kind"));
+
+        let mut tobe = MockNode::new("root");
+        tobe.add_property("id", "iak");
+        tobe.add_property("kind", "b");
+        assert_eq!(root, XmlTree::new(tobe.clone(), None));
+        assert!(root.remove_property("id").is_some());
+        assert_eq!(root.remove_property("id"), None);
+        assert!(!root.node().contains_key("id"));
+    }
+    #[test]
+    fn for_each_mut_test() {
+        let mut root = XmlTree::new(MockNode::new("root"), None);
+        let mut child = XmlTree::new(MockNode::new("child"), None);
+        child.append_children(XmlTree::new(MockNode::new("grandchild"), None));
+        root.append_children(child);
+        let mut visited = vec![];
+        root.for_each_mut(|tree| {
+            visited.push(tree.node().value());
+            tree.set_property("seen", "true");
+        });
+        assert_eq!(visited, vec!["root", "child", "grandchild"]);
+        assert_eq!(root.get_elements_by_key_value("seen", "true").len(), 3);
+    }
+    #[test]
+    fn find_mut_test() {
+        let mut child = leaf("child");
+        child.append_children(leaf("grandchild"));
+        let mut root = leaf("root");
+        root.append_children(child);
+        root.append_children(leaf("child"));
 
-    //let mut root = XmlTree {
-    //root: root,
-    //children: None,
-    //_marker: Default::default(),
-    //};
-    //root.change_property("id", "iak");
-    //let mut tobe = MockNode::new("root");
-    //tobe.add_property("id", "iak");
+        let grandchild = root.find_by_name_mut("grandchild").unwrap();
+        grandchild.set_property("id", "g");
+        grandchild.append_children(leaf("new"));
+        assert_eq!(root.get_elements_by_key_value("id", "g").len(), 1);
+        assert_eq!(root.get_elements_by_node_value("new").len(), 1);
+        root.find_by_name_mut("child")
+            .unwrap()
+            .node_mut()
+            .add_property("first", "true");
+        assert!(root.child(0).unwrap().node().contains_key("first"));
+        assert!(!root.child(1).unwrap().node().contains_key("first"));
+        let found = root
+            .find_mut(|tree| tree.node().contains_key("first"))
+            .unwrap();
+        assert_eq!(found.node().value(), "child");
+        assert!(root.find_by_name_mut("none").is_none());
+        assert!(root.find_mut(|tree| tree.len() == 2).is_some());
 
-    //let mut tobe = XmlTree {
-    //root: tobe,
-    //children: None,
-    //_marker: Default::default(),
-    //};
-    //assert_eq!(root, tobe);
-    //}
+        root.child_mut(1).unwrap().set_property("second", "true");
+        root.last_child_mut().unwrap().append_children(leaf("last"));
+        root.first_child_mut().unwrap().remove_child(0);
+        assert!(root.child(1).unwrap().node().contains_key("second"));
+        assert_eq!(child_values(root.child(1).unwrap()), vec!["last"]);
+        assert!(root.child(0).unwrap().is_empty());
+        assert!(root.child_mut(2).is_none());
+        for child in root.children_mut() {
+            child.rename_property("first", "seen");
+        }
+        assert_eq!(root.get_elements_by_key_value("seen", "true").len(), 1);
+        assert!(leaf("empty").first_child_mut().is_none());
+    }
     #[test]
     fn concat_all_text_test() {
         let mut root = XmlTree {
@@ -169,7 +460,16 @@ mod xml_tree_tests {
         };
         span.append_children(text_node);
         root.append_children(span);
-        assert_eq!(root.concat_all_text(), "hello world");
+        assert_eq!(root.concat_all_text(), "helloworld");
+
+        let mut comment = MockNode::new("comment");
+        comment.change_type(NodeType::Comment);
+        root.append_children(XmlTree {
+            root: comment,
+            children: None,
+            _marker: Default::default(),
+        });
+        assert_eq!(root.concat_all_text(), "helloworld");
     }
     #[test]
     fn text_contents_test() {
@@ -186,7 +486,143 @@ mod xml_tree_tests {
             _marker: Default::default(),
         };
         root.append_children(text_child);
-        assert_eq!(root.text_contents(), Some(vec!["text-content"]))
+        assert_eq!(root.text_contents(), Some(vec!["text-content"]));
+
+        let mut cdata_node = MockNode::new("a < b");
+        cdata_node.change_type(NodeType::CData);
+        root.append_children(XmlTree {
+            root: cdata_node,
+            children: None,
+            _marker: Default::default(),
+        });
+        assert_eq!(root.text_contents(), Some(vec!["text-content", "a < b"]));
+        assert_eq!(root.concat_all_text(), "text-contenta < b");
+    }
+    #[test]
+    fn to_xml_string_test() {
+        let mut root = XmlTree::new(MockNode::new("root"), None);
+        assert_eq!(root.to_xml_string(), "<root/>");
+        let mut text_node = MockNode::new("a &lt; b");
+        text_node.change_type(NodeType::Text);
+        root.append_children(XmlTree::new(text_node, None));
+        let mut comment = MockNode::new(" note ");
+        comment.change_type(NodeType::Comment);
+        root.append_children(XmlTree::new(comment, None));
+        root.append_children(XmlTree::new(MockNode::new("child"), None));
+        assert_eq!(
+            root.to_xml_string(),
+            "<root>a &lt; b<!-- note --><child/></root>"
+        );
+    }
+    #[test]
+    fn accessors_test() {
+        let mut root = XmlTree::new(MockNode::new("root"), None);
+        assert!(root.children().is_empty());
+        assert!(root.is_empty());
+        assert_eq!(root.first_child(), None);
+        root.node_mut().add_property("id", "r");
+        assert_eq!(root.node().get("id"), Some("r"));
+
+        let first = XmlTree::new(MockNode::new("first"), None);
+        let last = XmlTree::new(MockNode::new("last"), None);
+        root.append_children(first.clone());
+        root.append_children(last.clone());
+        assert_eq!(root.len(), 2);
+        assert_eq!(root.children(), &[first.clone(), last.clone()]);
+        assert_eq!(root.child(1), Some(&last));
+        assert_eq!(root.child(2), None);
+        assert_eq!(root.first_child(), Some(&first));
+        assert_eq!(root.last_child(), Some(&last));
+        assert_eq!(root.first_child().unwrap().node().value(), "first");
+
+        let empty = XmlTree::new(MockNode::new("empty"), Some(Box::new(vec![])));
+        assert_eq!(empty.len(), 0);
+        assert_eq!(empty.last_child(), None);
+    }
+    fn leaf(value: &str) -> XmlTree<'_, MockNode<'_>> {
+        XmlTree::new(MockNode::new(value), None)
+    }
+    fn child_values<'a>(tree: &XmlTree<'a, MockNode<'a>>) -> Vec<&'a str> {
+        tree.children()
+            .iter()
+            .map(|child| child.node().value())
+            .collect()
+    }
+    #[test]
+    fn insert_child_test() {
+        let mut root = leaf("root");
+        root.insert_child(0, leaf("b"));
+        assert_eq!(child_values(&root), vec!["b"]);
+        root.prepend_child(leaf("a"));
+        root.insert_child(2, leaf("d"));
+        root.insert_child(2, leaf("c"));
+        assert_eq!(child_values(&root), vec!["a", "b", "c", "d"]);
+
+        let mut empty = XmlTree::new(MockNode::new("root"), Some(Box::new(vec![])));
+        empty.prepend_child(leaf("a"));
+        assert_eq!(child_values(&empty), vec!["a"]);
+    }
+    #[test]
+    #[should_panic(expected = "child index 1 out of bounds")]
+    fn insert_child_out_of_bounds_test() {
+        leaf("root").insert_child(1, leaf("a"));
+    }
+    #[test]
+    fn remove_and_replace_child_test() {
+        let mut root = leaf("root");
+        root.append_children(leaf("a"));
+        root.append_children(leaf("b"));
+        assert_eq!(root.replace_child(1, leaf("c")), leaf("b"));
+        assert_eq!(child_values(&root), vec!["a", "c"]);
+        assert_eq!(root.remove_child(0), leaf("a"));
+        assert_eq!(root.remove_child(0), leaf("c"));
+        assert_eq!(root, leaf("root"));
+        assert_eq!(root.to_xml_string(), "<root/>");
+    }
+    #[test]
+    #[should_panic(expected = "child index 0 out of bounds")]
+    fn remove_child_out_of_bounds_test() {
+        XmlTree::new(MockNode::new("root"), Some(Box::new(vec![]))).remove_child(0);
+    }
+    #[test]
+    fn retain_and_take_children_test() {
+        let mut root = leaf("root");
+        root.retain_children(|_| false);
+        assert_eq!(root, leaf("root"));
+        for value in ["keep", "drop", "keep"] {
+            root.append_children(leaf(value));
+        }
+        root.retain_children(|child| child.node().value() == "keep");
+        assert_eq!(child_values(&root), vec!["keep", "keep"]);
+        root.retain_children(|_| false);
+        assert_eq!(root, leaf("root"));
+
+        root.append_children(leaf("a"));
+        assert_eq!(root.take_children(), vec![leaf("a")]);
+        assert_eq!(root.take_children(), vec![]);
+        assert!(root.is_empty());
+    }
+    #[test]
+    fn detach_test() {
+        let mut b = leaf("b");
+        b.append_children(leaf("c"));
+        let mut root = leaf("root");
+        root.append_children(b);
+        root.append_children(leaf("c"));
+        root.append_children(leaf("d"));
+
+        let c = root.detach(|tree| tree.node().value() == "c").unwrap();
+        assert_eq!(c, leaf("c"));
+        assert_eq!(root.child(0), Some(&leaf("b")));
+        assert_eq!(child_values(&root), vec!["b", "c", "d"]);
+        let b = root.detach(|tree| tree.node().value() == "b").unwrap();
+        assert_eq!(b, leaf("b"));
+        assert_eq!(root.detach(|tree| tree.node().value() == "root"), None);
+        assert_eq!(root.detach(|tree| tree.node().value() == "e"), None);
+        root.detach(|_| true);
+        root.detach(|_| true);
+        assert_eq!(root, leaf("root"));
+        assert_eq!(root.detach(|_| true), None);
     }
     #[test]
     fn get_elements_by_key_value_test() {
@@ -284,8 +720,9 @@ mod xml_tree_tests {
     }
 }
 
+#[cfg(test)]
 mod mock_node {
-    use std::collections::HashMap;
+    use std::{borrow::Cow, collections::HashMap};
 
     use crate::xml::trees::nodes::{
         node_interface::{
@@ -313,32 +750,46 @@ mod mock_node {
         pub fn change_type(&mut self, node_type: NodeType) {
             self.node_type = node_type
         }
-        pub fn save_property_value(&mut self, value: &'a str) {
-            self.stack.push(value)
-        }
     }
     impl<'a> ElementInterface<'a> for MockNode<'a> {
-        fn change(&mut self, value: &'a str) -> () {
+        fn change(&mut self, value: &'a str) {
             self.value = value
         }
         fn value(&self) -> &'a str {
             self.value
         }
+        fn decoded_value(&self) -> &str {
+            self.value
+        }
     }
     impl<'a> PropertyInterface<'a> for MockNode<'a> {
-        //fn change_property(&mut self, key: &'a str, new_value: &'a str) -> () {
-        //self.remove_property(key);
-        //self.add_property(key, new_value);
-        //}
-        //fn remove_property(&mut self, key: &'a str) -> Option<PropertyValue> {
-        //if self.contains_key(key) {
-        //self.remove_property(key);
-        //Some(self.stack.clone())
-        //} else {
-        //None
-        //}
-        //}
-        fn add_property(&mut self, key: &str, value: &str) -> () {
+        fn set_property(&mut self, key: &'a str, value: &'a str) {
+            self.key_value
+                .insert(key.to_string(), vec![value.to_string()]);
+        }
+        fn remove_property(&mut self, key: &str) -> Option<PropertyValue<'a>> {
+            self.key_value
+                .remove(key)
+                .map(|values| values.into_iter().map(Cow::Owned).collect())
+        }
+        fn remove_property_value(&mut self, key: &str, value: &str) -> bool {
+            let Some(values) = self.key_value.get_mut(key) else {
+                return false;
+            };
+            let len = values.len();
+            values.retain(|v| v != value);
+            values.len() != len
+        }
+        fn rename_property(&mut self, from: &str, to: &'a str) -> bool {
+            match self.key_value.remove(from) {
+                Some(values) => {
+                    self.key_value.insert(to.to_string(), values);
+                    true
+                }
+                None => false,
+            }
+        }
+        fn add_property(&mut self, key: &str, value: &str) {
             if self.key_value.contains_key(key) {
                 self.key_value
                     .get_mut(key)
@@ -363,7 +814,13 @@ mod mock_node {
                 false
             }
         }
-        fn keys(&self) -> Option<Vec<PropertyKey>> {
+        fn get(&self, key: &str) -> Option<&str> {
+            self.key_value
+                .get(key)
+                .and_then(|values| values.first())
+                .map(String::as_str)
+        }
+        fn keys(&self) -> Option<Vec<PropertyKey<'_>>> {
             //if self.key_value.keys().len() == 0 {
             //None
             //} else {
@@ -371,7 +828,7 @@ mod mock_node {
             //}
             None
         }
-        fn values(&self) -> Option<Vec<&PropertyValue>> {
+        fn values(&self) -> Option<Vec<&PropertyValue<'_>>> {
             None
         }
     }
@@ -382,5 +839,14 @@ mod mock_node {
         fn is_text_type(&self) -> bool {
             self.node_type == NodeType::Text
         }
+        fn is_comment_type(&self) -> bool {
+            self.node_type == NodeType::Comment
+        }
+        fn is_cdata_type(&self) -> bool {
+            self.node_type == NodeType::CData
+        }
+        fn is_processing_instruction_type(&self) -> bool {
+            self.node_type == NodeType::ProcessingInstruction
+        }
     }
 }
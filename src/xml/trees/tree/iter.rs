@@ -0,0 +1,206 @@
+use std::collections::VecDeque;
+
+use crate::xml::trees::{nodes::node_interface::NodeInterface, tree::XmlTree};
+
+/// Entering or leaving a tree while walking through it.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Edge<'t, 'a, T: NodeInterface<'a>> {
+    /// Before the children, where the start tag is.
+    Start(&'t XmlTree<'a, T>),
+    /// After the children, where the end tag is.
+    End(&'t XmlTree<'a, T>),
+}
+
+impl<'a, T: NodeInterface<'a>> Clone for Edge<'_, 'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T: NodeInterface<'a>> Copy for Edge<'_, 'a, T> {}
+
+/// The `Start` and `End` edges of a tree and everything below it, in
+/// document order. Made by `XmlTree::traverse`.
+#[derive(Clone, Debug)]
+pub struct Traverse<'t, 'a, T: NodeInterface<'a>> {
+    /// The edges still to come, the next one last.
+    stack: Vec<Edge<'t, 'a, T>>,
+}
+
+impl<'t, 'a, T: NodeInterface<'a>> Traverse<'t, 'a, T> {
+    pub(super) fn new(tree: &'t XmlTree<'a, T>) -> Self {
+        Traverse {
+            stack: vec![Edge::Start(tree)],
+        }
+    }
+}
+
+impl<'t, 'a, T: NodeInterface<'a>> Iterator for Traverse<'t, 'a, T> {
+    type Item = Edge<'t, 'a, T>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let edge = self.stack.pop()?;
+        if let Edge::Start(tree) = edge {
+            self.stack.push(Edge::End(tree));
+            self.stack
+                .extend(tree.children().iter().rev().map(Edge::Start));
+        }
+        Some(edge)
+    }
+}
+
+/// A tree and everything below it in document order, parents before
+/// their children. Made by `XmlTree::descendants`.
+#[derive(Clone, Debug)]
+pub struct Descendants<'t, 'a, T: NodeInterface<'a>> {
+    /// The trees still to come, the next one last.
+    stack: Vec<&'t XmlTree<'a, T>>,
+}
+
+impl<'t, 'a, T: NodeInterface<'a>> Descendants<'t, 'a, T> {
+    pub(super) fn new(tree: &'t XmlTree<'a, T>) -> Self {
+        Descendants { stack: vec![tree] }
+    }
+}
+
+impl<'t, 'a, T: NodeInterface<'a>> Iterator for Descendants<'t, 'a, T> {
+    type Item = &'t XmlTree<'a, T>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let tree = self.stack.pop()?;
+        self.stack.extend(tree.children().iter().rev());
+        Some(tree)
+    }
+}
+
+/// A tree and everything below it level by level. Made by
+/// `XmlTree::breadth_first`.
+#[derive(Clone, Debug)]
+pub struct BreadthFirst<'t, 'a, T: NodeInterface<'a>> {
+    queue: VecDeque<&'t XmlTree<'a, T>>,
+}
+
+impl<'t, 'a, T: NodeInterface<'a>> BreadthFirst<'t, 'a, T> {
+    pub(super) fn new(tree: &'t XmlTree<'a, T>) -> Self {
+        BreadthFirst {
+            queue: VecDeque::from([tree]),
+        }
+    }
+}
+
+impl<'t, 'a, T: NodeInterface<'a>> Iterator for BreadthFirst<'t, 'a, T> {
+    type Item = &'t XmlTree<'a, T>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let tree = self.queue.pop_front()?;
+        self.queue.extend(tree.children());
+        Some(tree)
+    }
+}
+
+/// The element trees among the descendants. Made by `XmlTree::elements`.
+#[derive(Clone, Debug)]
+pub struct Elements<'t, 'a, T: NodeInterface<'a>> {
+    descendants: Descendants<'t, 'a, T>,
+}
+
+impl<'t, 'a, T: NodeInterface<'a>> Elements<'t, 'a, T> {
+    pub(super) fn new(tree: &'t XmlTree<'a, T>) -> Self {
+        Elements {
+            descendants: Descendants::new(tree),
+        }
+    }
+}
+
+impl<'t, 'a, T: NodeInterface<'a>> Iterator for Elements<'t, 'a, T> {
+    type Item = &'t XmlTree<'a, T>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.descendants.find(|tree| tree.node().is_element_type())
+    }
+}
+
+/// The decoded text of the text and CDATA nodes among the descendants.
+/// Made by `XmlTree::texts`.
+#[derive(Clone, Debug)]
+pub struct Texts<'t, 'a, T: NodeInterface<'a>> {
+    descendants: Descendants<'t, 'a, T>,
+}
+
+impl<'t, 'a, T: NodeInterface<'a>> Texts<'t, 'a, T> {
+    pub(super) fn new(tree: &'t XmlTree<'a, T>) -> Self {
+        Texts {
+            descendants: Descendants::new(tree),
+        }
+    }
+}
+
+impl<'t, 'a, T: NodeInterface<'a>> Iterator for Texts<'t, 'a, T> {
+    type Item = &'t str;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.descendants
+            .find(|tree| tree.node().is_character_data())
+            .map(|tree| tree.node().decoded_value())
+    }
+}
+
+#[cfg(test)]
+mod iter_tests {
+    use crate::xml::trees::{
+        generators::xml_generator::XmlGenerator,
+        nodes::node_interface::{ElementInterface, PropertyInterface},
+    };
+
+    use super::*;
+    const SOURCE: &str = "<a><b><c/>one</b><!-- x --><d>two<e/></d></a>";
+    #[test]
+    fn descendants_test() {
+        let tree = XmlGenerator::gen(SOURCE);
+        let values: Vec<_> = tree.descendants().map(|tree| tree.node().value()).collect();
+        assert_eq!(values, vec!["a", "b", "c", "one", " x ", "d", "two", "e"]);
+        let first_leaf = tree.descendants().find(|tree| tree.is_empty());
+        assert_eq!(first_leaf.unwrap().node().value(), "c");
+        let leaf = XmlGenerator::gen("<a/>");
+        assert_eq!(leaf.descendants().count(), 1);
+    }
+    #[test]
+    fn traverse_test() {
+        let tree = XmlGenerator::gen("<a><b>one</b><c/></a>");
+        let edges: Vec<_> = tree
+            .traverse()
+            .map(|edge| match edge {
+                Edge::Start(tree) => format!("<{}>", tree.node().value()),
+                Edge::End(tree) => format!("</{}>", tree.node().value()),
+            })
+            .collect();
+        assert_eq!(edges.concat(), "<a><b><one></one></b><c></c></a>");
+        let post_order: Vec<_> = tree
+            .traverse()
+            .filter_map(|edge| match edge {
+                Edge::End(tree) => Some(tree.node().value()),
+                Edge::Start(_) => None,
+            })
+            .collect();
+        assert_eq!(post_order, vec!["one", "b", "c", "a"]);
+    }
+    #[test]
+    fn breadth_first_test() {
+        let tree = XmlGenerator::gen(SOURCE);
+        let values: Vec<_> = tree
+            .breadth_first()
+            .map(|tree| tree.node().value())
+            .collect();
+        assert_eq!(values, vec!["a", "b", " x ", "d", "c", "one", "two", "e"]);
+    }
+    #[test]
+    fn elements_and_texts_test() {
+        let tree = XmlGenerator::gen(SOURCE);
+        let names: Vec<_> = tree.elements().map(|tree| tree.node().value()).collect();
+        assert_eq!(names, vec!["a", "b", "c", "d", "e"]);
+        let tree = XmlGenerator::gen("<p>a &amp; b<![CDATA[<c>]]><i>d</i></p>");
+        assert_eq!(tree.texts().collect::<Vec<_>>(), vec!["a & b", "<c>", "d"]);
+        let tree =
+            XmlGenerator::gen(r#"<ul><li class="x">1</li><li>2</li><li class="x">3</li></ul>"#);
+        let second = tree
+            .elements()
+            .filter(|tree| tree.node().contains_key_value("class", "x"))
+            .nth(1);
+        assert_eq!(second.unwrap().texts().next(), Some("3"));
+    }
+}